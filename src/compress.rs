@@ -0,0 +1,108 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{copy, uuid, CliError};
+
+/// Appended to the name of every compressed file
+pub const SUFFIX: &str = ".zst";
+
+// The default level of zstd, which compresses saves well while keeping up
+// with the disk
+const LEVEL: i32 = 3;
+
+/// Compresses every file below `dir` with zstd, replacing each file by a
+/// compressed one named like it with [`SUFFIX`] appended. Files which are
+/// already compressed are left alone.
+pub fn compress(dir: &Path) -> Result<(), CliError> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    files.retain(|file| !is_compressed_file(file));
+    copy::for_each_parallel(&files, |file| {
+        let mut name = file.clone().into_os_string();
+        name.push(SUFFIX);
+        replace(file, &PathBuf::from(name), |input, output| {
+            zstd::stream::copy_encode(input, output, LEVEL)
+        })
+    })
+}
+
+/// Replaces every compressed file below `dir` by the file it was compressed
+/// from.
+pub fn decompress(dir: &Path) -> Result<(), CliError> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    files.retain(|file| is_compressed_file(file));
+    copy::for_each_parallel(&files, |file| {
+        replace(file, &original(file), |input, output| {
+            zstd::stream::copy_decode(input, output)
+        })
+    })
+}
+
+/// Whether any file below `dir` is compressed.
+pub fn is_compressed(dir: &Path) -> Result<bool, CliError> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    Ok(files.iter().any(|file| is_compressed_file(file)))
+}
+
+/// Reads the contents of a compressed file as they were before compressing it.
+pub fn read<F, T>(path: &Path, reader: F) -> Result<T, CliError>
+where
+    F: FnOnce(&mut dyn Read) -> std::io::Result<T>,
+{
+    let mut decoder = zstd::Decoder::new(File::open(path)?)?;
+    Ok(reader(&mut decoder)?)
+}
+
+// Writes `to` from `from` and removes `from`. The result is written next to
+// `to` first, so a failure never leaves a partial file under its name
+fn replace<F>(from: &Path, to: &Path, convert: F) -> Result<(), CliError>
+where
+    F: FnOnce(&mut dyn Read, &mut dyn Write) -> std::io::Result<()>,
+{
+    let mut partial = to.to_path_buf().into_os_string();
+    partial.push(format!(".{}", uuid()));
+    let partial = PathBuf::from(partial);
+    let result = (|| -> std::io::Result<()> {
+        let mut input = BufReader::new(File::open(from)?);
+        let mut output = BufWriter::new(File::create(&partial)?);
+        convert(&mut input, &mut output)?;
+        output.flush()?;
+        drop(output);
+        fs::rename(&partial, to)
+    })();
+    if let Err(error) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(error.into());
+    }
+    fs::remove_file(from)?;
+    Ok(())
+}
+
+fn original(path: &Path) -> PathBuf {
+    path.to_str()
+        .and_then(|path| path.strip_suffix(SUFFIX))
+        .map_or_else(|| path.to_path_buf(), PathBuf::from)
+}
+
+fn is_compressed_file(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(SUFFIX)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), CliError> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
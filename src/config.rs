@@ -0,0 +1,577 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use toml::Table;
+use toml_edit::DocumentMut;
+
+use crate::copy::Engine;
+use crate::{nauttaja_dir, CliError, NAUTTAJA_GAMEDB_FILE};
+
+const NAUTTAJA_CONFIG_FILE: &str = "config.toml";
+
+const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("NAUTTAJA_NOITA_DIR", "noita_root_dir"),
+    ("NAUTTAJA_IMMUTABLE_SAVES", "immutable_saves"),
+    ("NAUTTAJA_DEDUP_SAVES", "dedup_saves"),
+    ("NAUTTAJA_COMPRESS_SAVES", "compress_saves"),
+    ("NAUTTAJA_SUSPICIOUS_SAVE", "suspicious_save"),
+    ("NAUTTAJA_BACKUP", "backup"),
+    ("NAUTTAJA_BACKUP_RETENTION", "backup_retention"),
+    ("NAUTTAJA_PRE_LOAD_SNAPSHOT", "pre_load_snapshot"),
+    ("NAUTTAJA_NOTIFY_SESSIONS", "notify_sessions"),
+    ("NAUTTAJA_DELETE_GRACE_DAYS", "delete_grace_days"),
+    ("NAUTTAJA_TRASH_RETENTION_DAYS", "trash_retention_days"),
+    ("NAUTTAJA_TRASH_QUOTA_MIB", "trash_quota_mib"),
+    ("NAUTTAJA_STEAM_CLOUD", "steam_cloud"),
+    ("NAUTTAJA_STEAM_DIR", "steam_root_dir"),
+    ("NAUTTAJA_NAME_TEMPLATE", "name_template"),
+    ("NAUTTAJA_PRUNE_KEEP_LAST", "prune_keep_last"),
+    ("NAUTTAJA_PRUNE_KEEP_DAYS", "prune_keep_days"),
+    ("NAUTTAJA_LOW_DISK_SPACE_MIB", "low_disk_space_mib"),
+    ("NAUTTAJA_COPY_ENGINES", "copy_engines"),
+    ("NAUTTAJA_LIST_NAME_WIDTH", "list_name_width"),
+    ("NAUTTAJA_LIST_NAME_ELLIPSIS", "list_name_ellipsis"),
+    ("NAUTTAJA_LIST_NOTE_WIDTH", "list_note_width"),
+    ("NAUTTAJA_LIST_NOTE_ELLIPSIS", "list_note_ellipsis"),
+];
+
+// Settings which only make sense on the machine they were made on, so they are
+// neither exported nor imported
+const MACHINE_SETTINGS: &[&str] = &["noita_root_dir", "steam_root_dir"];
+
+/// Placeholders which may be used in `name_template`
+pub const NAME_PLACEHOLDERS: &[&str] = &["seed", "depth", "biome", "orbs", "date", "time"];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub noita_root_dir: String,
+    #[serde(default)]
+    pub immutable_saves: bool,
+    #[serde(default)]
+    pub dedup_saves: bool,
+    #[serde(default)]
+    pub compress_saves: bool,
+    #[serde(default)]
+    pub suspicious_save: SuspiciousSavePolicy,
+    #[serde(default)]
+    pub backup: BackupPolicy,
+    #[serde(default = "default_backup_retention")]
+    pub backup_retention: usize,
+    #[serde(default)]
+    pub pre_load_snapshot: bool,
+    #[serde(default)]
+    pub notify_sessions: bool,
+    #[serde(default = "default_delete_grace_days")]
+    pub delete_grace_days: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash_retention_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash_quota_mib: Option<u64>,
+    #[serde(default)]
+    pub steam_cloud: SteamCloudPolicy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steam_root_dir: Option<String>,
+    #[serde(default = "default_name_template")]
+    pub name_template: String,
+    #[serde(default = "default_prune_keep_last")]
+    pub prune_keep_last: usize,
+    #[serde(default = "default_prune_keep_days")]
+    pub prune_keep_days: u32,
+    #[serde(default = "default_low_disk_space_mib")]
+    pub low_disk_space_mib: u64,
+    #[serde(default = "default_copy_engines")]
+    pub copy_engines: Vec<Engine>,
+    #[serde(default = "default_list_name_width")]
+    pub list_name_width: usize,
+    #[serde(default)]
+    pub list_name_ellipsis: Ellipsis,
+    #[serde(default)]
+    pub list_note_width: usize,
+    #[serde(default)]
+    pub list_note_ellipsis: Ellipsis,
+    #[serde(default)]
+    pub defaults: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SuspiciousSavePolicy {
+    #[default]
+    Warn,
+    Skip,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupPolicy {
+    #[default]
+    Always,
+    Ask,
+    Never,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SteamCloudPolicy {
+    #[default]
+    Warn,
+    Ask,
+    Ignore,
+}
+
+/// Where text cut short to fit its column is marked with an ellipsis
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Ellipsis {
+    #[default]
+    End,
+    Middle,
+    Start,
+}
+
+fn default_backup_retention() -> usize {
+    5
+}
+
+fn default_delete_grace_days() -> u32 {
+    3
+}
+
+fn default_name_template() -> String {
+    "{seed}-{depth}-{date}".to_string()
+}
+
+fn default_prune_keep_last() -> usize {
+    20
+}
+
+fn default_prune_keep_days() -> u32 {
+    30
+}
+
+fn default_low_disk_space_mib() -> u64 {
+    1024
+}
+
+fn default_list_name_width() -> usize {
+    40
+}
+
+// Hard links are left out, since the copies would share their files
+fn default_copy_engines() -> Vec<Engine> {
+    vec![Engine::Reflink, Engine::Parallel, Engine::Plain]
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            noita_root_dir: String::new(),
+            immutable_saves: false,
+            dedup_saves: false,
+            compress_saves: false,
+            suspicious_save: SuspiciousSavePolicy::default(),
+            backup: BackupPolicy::default(),
+            backup_retention: default_backup_retention(),
+            pre_load_snapshot: false,
+            notify_sessions: false,
+            delete_grace_days: default_delete_grace_days(),
+            trash_retention_days: None,
+            trash_quota_mib: None,
+            steam_cloud: SteamCloudPolicy::default(),
+            steam_root_dir: None,
+            name_template: default_name_template(),
+            prune_keep_last: default_prune_keep_last(),
+            prune_keep_days: default_prune_keep_days(),
+            low_disk_space_mib: default_low_disk_space_mib(),
+            copy_engines: default_copy_engines(),
+            list_name_width: default_list_name_width(),
+            list_name_ellipsis: Ellipsis::default(),
+            list_note_width: 0,
+            list_note_ellipsis: Ellipsis::default(),
+            defaults: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    fn validate(&self) -> Result<(), String> {
+        if self.noita_root_dir.is_empty() {
+            return Err("noita_root_dir is empty, run nauttaja set-noita-dir <path to Noitas root directory>".to_string());
+        }
+        if self.backup_retention == 0 {
+            return Err("backup_retention must be at least 1".to_string());
+        }
+        if self.name_template.trim().is_empty() {
+            return Err("name_template is empty".to_string());
+        }
+        let mut rest = self.name_template.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => return Err("name_template has an unclosed {".to_string()),
+            };
+            let placeholder = &rest[start + 1..end];
+            if !NAME_PLACEHOLDERS.contains(&placeholder) {
+                return Err(format!(
+                    "name_template has an unknown placeholder {{{}}}, use one of {{{}}}",
+                    placeholder,
+                    NAME_PLACEHOLDERS.join("}, {")
+                ));
+            }
+            rest = &rest[end + 1..];
+        }
+        Ok(())
+    }
+}
+
+pub fn config_file() -> Result<PathBuf, CliError> {
+    Ok(nauttaja_dir()?.join(NAUTTAJA_CONFIG_FILE))
+}
+
+pub fn load() -> Result<Config, CliError> {
+    let file = config_file()?;
+    let invalid = |message: String| {
+        CliError::Config(format!(
+            "Invalid configuration in [{}]\n{}",
+            file.display(),
+            message
+        ))
+    };
+
+    let mut table = if file.exists() || migrate_from_gamedb()? {
+        let data = fs::read_to_string(&file)?;
+        let table: Table = toml::from_str(&data).map_err(|error| invalid(error.to_string()))?;
+        if table.contains_key("noita_root_dir") {
+            // Deserialize from the text, so errors point at the offending line
+            toml::from_str::<Config>(&data).map_err(|error| invalid(error.to_string()))?;
+        }
+        table
+    } else {
+        Table::new()
+    };
+
+    for (variable, key) in ENV_OVERRIDES {
+        if let Ok(raw) = env::var(variable) {
+            table.insert(key.to_string(), env_value(&raw));
+        }
+    }
+    if !table.contains_key("noita_root_dir") {
+        return Err(CliError::Io(Error::new(
+            ErrorKind::NotFound,
+            "Failed to find config file",
+        )));
+    }
+
+    let config = Config::deserialize(table).map_err(|error| {
+        CliError::Config(format!(
+            "Invalid configuration in NAUTTAJA_* environment variables\n{}",
+            error
+        ))
+    })?;
+    config.validate().map_err(invalid)?;
+    Ok(config)
+}
+
+// Values are parsed like TOML values, falling back to plain strings
+fn env_value(raw: &str) -> toml::Value {
+    toml::from_str::<Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+pub fn set_noita_root_dir(noita_root_dir: &str) -> Result<(), CliError> {
+    let file = config_file()?;
+    fs::create_dir_all(nauttaja_dir()?)?;
+    if !file.exists() && !migrate_from_gamedb()? {
+        let config = Config {
+            noita_root_dir: noita_root_dir.to_string(),
+            ..Default::default()
+        };
+        fs::write(&file, template(&config)?)?;
+        return Ok(());
+    }
+
+    // Edit the document in place, so comments and formatting are preserved
+    let mut document: DocumentMut = fs::read_to_string(&file)?.parse().map_err(|error| {
+        CliError::Config(format!(
+            "Invalid configuration in [{}]\n{}",
+            file.display(),
+            error
+        ))
+    })?;
+    document["noita_root_dir"] = toml_edit::value(noita_root_dir);
+    fs::write(&file, document.to_string())?;
+    Ok(())
+}
+
+/// Settings of another machine, along with how they differ from the settings
+/// in use
+pub struct Import {
+    pub config: Config,
+    pub changes: Vec<(String, Option<Value>, Option<Value>)>,
+    pub kept: Vec<String>,
+}
+
+/// Writes the settings in use to `to`, leaving out those which only make sense
+/// on this machine. Returns the number of settings written.
+pub fn export(config: &Config, to: &Path) -> Result<usize, CliError> {
+    let mut table = Table::try_from(config).map_err(|error| CliError::Config(error.to_string()))?;
+    for key in MACHINE_SETTINGS {
+        table.remove(*key);
+    }
+    if config.defaults.is_empty() {
+        table.remove("defaults");
+    }
+    let data = toml::to_string(&table).map_err(|error| CliError::Config(error.to_string()))?;
+    fs::write(
+        to,
+        format!(
+            "# Settings exported by nauttaja, imported by `nauttaja config import <file>`\n\n{}",
+            data
+        ),
+    )?;
+    Ok(table.len())
+}
+
+/// The settings in `from` in place of those in use, except for those which
+/// only make sense on this machine. Nothing is written yet.
+pub fn import(config: &Config, from: &Path) -> Result<Import, CliError> {
+    let invalid = |message: String| {
+        CliError::Config(format!(
+            "Invalid settings in [{}]\n{}",
+            from.display(),
+            message
+        ))
+    };
+    let mut imported: Table =
+        toml::from_str(&fs::read_to_string(from)?).map_err(|error| invalid(error.to_string()))?;
+    let current = Table::try_from(config).map_err(|error| CliError::Config(error.to_string()))?;
+
+    let mut kept = Vec::new();
+    for key in MACHINE_SETTINGS {
+        if imported.remove(*key).is_some() {
+            kept.push(key.to_string());
+        }
+        if let Some(value) = current.get(*key) {
+            imported.insert(key.to_string(), value.clone());
+        }
+    }
+    let imported = Config::deserialize(imported).map_err(|error| invalid(error.to_string()))?;
+    imported.validate().map_err(invalid)?;
+
+    // Compared once defaults are filled in, so settings left out of the file
+    // only count as changed when their values differ
+    let (current, new) = match (
+        serde_json::to_value(config)?,
+        serde_json::to_value(&imported)?,
+    ) {
+        (Value::Object(current), Value::Object(new)) => (current, new),
+        _ => unreachable!("Config serializes to a map"),
+    };
+    let mut keys: Vec<&String> = current.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    let changes = keys
+        .into_iter()
+        .filter(|key| current.get(*key) != new.get(*key))
+        .map(|key| {
+            (
+                key.clone(),
+                current.get(key).cloned(),
+                new.get(key).cloned(),
+            )
+        })
+        .collect();
+    Ok(Import {
+        config: imported,
+        changes,
+        kept,
+    })
+}
+
+/// Replaces the config file with the given settings.
+pub fn write(config: &Config) -> Result<(), CliError> {
+    fs::write(config_file()?, template(config)?)?;
+    Ok(())
+}
+
+// Settings used to be stored in the gamedb, next to the saves
+fn migrate_from_gamedb() -> Result<bool, CliError> {
+    if crate::read_only() {
+        return Ok(false);
+    }
+    let gamedb_file = nauttaja_dir()?.join(NAUTTAJA_GAMEDB_FILE);
+    if !gamedb_file.exists() {
+        return Ok(false);
+    }
+    let gamedb: Value = match serde_json::from_str(&fs::read_to_string(gamedb_file)?) {
+        Ok(gamedb) => gamedb,
+        // A malformed gamedb is reported once the saves are loaded
+        Err(_) => return Ok(false),
+    };
+    match gamedb.get("config") {
+        Some(legacy) => {
+            let config: Config = serde_json::from_value(legacy.clone())?;
+            fs::write(config_file()?, template(&config)?)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+fn template(config: &Config) -> Result<String, CliError> {
+    let copy_engines: Vec<String> = config
+        .copy_engines
+        .iter()
+        .map(|engine| format!("\"{}\"", engine.name()))
+        .collect();
+    let copy_engines = format!("[{}]", copy_engines.join(", "));
+    let mut template = format!(
+        r#"# Configuration for nauttaja
+
+# Path to Noitas root directory, also set by `nauttaja set-noita-dir <path>`
+noita_root_dir = {noita_root_dir}
+
+# Mark every new save read-only once it has been created
+immutable_saves = {immutable_saves}
+
+# Keep the files of new saves once each in a store by their hash, hard linking
+# them into every save having them instead of copying them
+dedup_saves = {dedup_saves}
+
+# Compress the world of every new save with zstd
+compress_saves = {compress_saves}
+
+# What to do when the current save looks suspicious before saving, "warn" or "skip"
+suspicious_save = {suspicious_save}
+
+# Whether `load` creates an emergency backup first, "always", "ask" or "never"
+backup = {backup}
+
+# Number of emergency backups to keep
+backup_retention = {backup_retention}
+
+# Save the current save as "pre-load-<generated name>" before every `load`
+pre_load_snapshot = {pre_load_snapshot}
+
+# Show a desktop notification summing up each session of Noita seen by `watch`
+notify_sessions = {notify_sessions}
+
+# Days to keep the files of deleted saves before purging them, 0 deletes at once
+delete_grace_days = {delete_grace_days}
+
+# Days to keep saves in the trash before deleting them, kept until deleted by
+# hand when left out
+{trash_retention_days}
+
+# Size in MiB the trash may take up, beyond which the saves removed the longest
+# ago are deleted, no limit when left out
+{trash_quota_mib}
+
+# What to do before `load` when Steam Cloud may overwrite the loaded save,
+# "warn", "ask" or "ignore"
+steam_cloud = {steam_cloud}
+
+# Path to Steams root directory, found automatically when left out
+{steam_root_dir}
+
+# Name of saves which are named automatically, such as `nauttaja save` without
+# a name. Placeholders are filled in from the current save: {{seed}}, {{depth}},
+# {{biome}}, {{orbs}}, {{date}} and {{time}}
+name_template = {name_template}
+
+# Saves kept by `nauttaja prune`, which moves every other save to the trash:
+# the latest saves, and those made within the last number of days
+prune_keep_last = {prune_keep_last}
+prune_keep_days = {prune_keep_days}
+
+# Mebibytes of free space below which `status`, `autosave` and `watch` warn
+# about the disk holding the saves or Noita running full, 0 never warns
+low_disk_space_mib = {low_disk_space_mib}
+
+# How saves are copied, trying each engine in turn until one works between
+# the volumes involved: "reflink", "hardlink", "parallel" or "plain", where
+# "plain" works everywhere and is used when the others do not. With "hardlink"
+# copies share their files, so changing one changes the other as well, which is
+# why it is only used between saves and never to or from Noitas directory
+copy_engines = {copy_engines}
+
+# Terminal columns the names and notes of saves may take up in `list`, beyond
+# which they are cut short, 0 never cuts them short. The ellipsis marking what
+# was left out goes at the "end", in the "middle" or at the "start"
+list_name_width = {list_name_width}
+list_name_ellipsis = {list_name_ellipsis}
+list_note_width = {list_note_width}
+list_note_ellipsis = {list_note_ellipsis}
+
+# Default options per command, for example
+#
+# [defaults.load]
+# no-backup = true
+"#,
+        noita_root_dir = toml_edit::value(config.noita_root_dir.as_str()),
+        immutable_saves = config.immutable_saves,
+        dedup_saves = config.dedup_saves,
+        compress_saves = config.compress_saves,
+        suspicious_save = toml_value(&config.suspicious_save)?,
+        backup = toml_value(&config.backup)?,
+        backup_retention = config.backup_retention,
+        pre_load_snapshot = config.pre_load_snapshot,
+        notify_sessions = config.notify_sessions,
+        delete_grace_days = config.delete_grace_days,
+        steam_cloud = toml_value(&config.steam_cloud)?,
+        trash_retention_days = match config.trash_retention_days {
+            Some(days) => format!("trash_retention_days = {}", days),
+            None => "# trash_retention_days = 30".to_string(),
+        },
+        trash_quota_mib = match config.trash_quota_mib {
+            Some(quota) => format!("trash_quota_mib = {}", quota),
+            None => "# trash_quota_mib = 10240".to_string(),
+        },
+        name_template = toml_edit::value(config.name_template.as_str()),
+        prune_keep_last = config.prune_keep_last,
+        prune_keep_days = config.prune_keep_days,
+        low_disk_space_mib = config.low_disk_space_mib,
+        copy_engines = copy_engines,
+        list_name_width = config.list_name_width,
+        list_name_ellipsis = toml_value(&config.list_name_ellipsis)?,
+        list_note_width = config.list_note_width,
+        list_note_ellipsis = toml_value(&config.list_note_ellipsis)?,
+        steam_root_dir = match &config.steam_root_dir {
+            Some(steam_root_dir) => format!(
+                "steam_root_dir = {}",
+                toml_edit::value(steam_root_dir.as_str())
+            ),
+            None => "# steam_root_dir = \"C:\\\\Program Files (x86)\\\\Steam\"".to_string(),
+        },
+    );
+
+    if !config.defaults.is_empty() {
+        #[derive(Serialize)]
+        struct Defaults<'a> {
+            defaults: &'a BTreeMap<String, BTreeMap<String, Value>>,
+        }
+        let defaults = toml::to_string(&Defaults {
+            defaults: &config.defaults,
+        })
+        .map_err(|error| CliError::Config(error.to_string()))?;
+        template.push('\n');
+        template.push_str(&defaults);
+    }
+    Ok(template)
+}
+
+fn toml_value<T: Serialize>(value: &T) -> Result<String, CliError> {
+    let value = serde_json::to_value(value)?;
+    match value.as_str() {
+        Some(value) => Ok(toml_edit::value(value).to_string()),
+        None => Ok(value.to_string()),
+    }
+}
@@ -0,0 +1,522 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Error};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+
+use fs_extra::dir;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{fault, manifest, progress, uuid, CliError};
+
+// Written next to the copy to find out whether an engine works between two
+// volumes, and removed right away
+const PROBE_FILE: &str = ".nauttaja-copy-probe";
+
+// Copies into a directory which exists already are made here first, next to
+// the directory, and moved into it once complete
+const STAGING_PREFIX: &str = ".nauttaja-copy-";
+
+/// Ways of copying a directory. Reflinks and hard links share the data of the
+/// files instead of copying it, so they are nearly instant, but only work
+/// within a single volume and reflinks only on some file systems.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Engine {
+    Reflink,
+    Hardlink,
+    Parallel,
+    Plain,
+}
+
+/// Every engine, in the order they are tried when all of them are configured
+pub const ENGINES: &[Engine] = &[
+    Engine::Reflink,
+    Engine::Hardlink,
+    Engine::Parallel,
+    Engine::Plain,
+];
+
+impl Engine {
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Reflink => "reflink",
+            Engine::Hardlink => "hardlink",
+            Engine::Parallel => "parallel",
+            Engine::Plain => "plain",
+        }
+    }
+
+    /// Whether the copy shares its files with the original, so changing one
+    /// changes the other.
+    pub fn shares_files(self) -> bool {
+        self == Engine::Hardlink
+    }
+
+    /// Whether the engine works from the volume of `from` to the volume of the
+    /// directory `to`, found by trying it on a single file.
+    pub fn is_supported(self, from: &Path, to: &Path) -> bool {
+        let file = match first_file(from) {
+            Some(file) => file,
+            None => return true,
+        };
+        let probe = to.join(PROBE_FILE);
+        let supported = match self {
+            Engine::Reflink => reflink(&file, &probe).is_ok(),
+            Engine::Hardlink => fs::hard_link(&file, &probe).is_ok(),
+            Engine::Parallel | Engine::Plain => return true,
+        };
+        let _ = fs::remove_file(&probe);
+        supported
+    }
+
+    /// Copies the directory `from` into the directory `to`.
+    pub fn copy(self, from: &Path, to: &Path) -> Result<(), CliError> {
+        let target = to.join(from.file_name().unwrap_or_default());
+        match self {
+            Engine::Reflink => reflink(from, to),
+            Engine::Hardlink => hard_link_dir(from, &target, &mut 0),
+            Engine::Parallel => parallel_copy(from, &target),
+            Engine::Plain => plain_copy(from, to),
+        }
+    }
+}
+
+/// Copies the directory `from` into the directory `to` with the first engine of
+/// the chain which works between their volumes. The plain engine, which works
+/// everywhere, is used when the chain runs out. Returns the engine used.
+pub fn copy_dir(chain: &[Engine], from: &Path, to: &Path) -> Result<Engine, CliError> {
+    let name = from.file_name().unwrap_or_default();
+    let target = to.join(name);
+    // An engine failing midway would leave files in the way of the next one,
+    // and those cannot be told apart from what was there before
+    if target.exists() {
+        let staging = to.join(format!("{}{}", STAGING_PREFIX, uuid()));
+        fs::create_dir(&staging)?;
+        let result = copy_dir(chain, from, &staging)
+            .and_then(|engine| move_into(&staging.join(name), &target).map(|_| engine));
+        set_readonly(&staging, false)?;
+        fs::remove_dir_all(&staging)?;
+        return result;
+    }
+    for &engine in chain {
+        if engine == Engine::Plain {
+            break;
+        }
+        if !engine.is_supported(from, to) {
+            debug!(
+                "The {} engine does not work from [{}] to [{}]",
+                engine.name(),
+                from.display(),
+                to.display()
+            );
+            continue;
+        }
+        match engine.copy(from, to).and_then(|_| fault::check("copy")) {
+            Ok(()) => return Ok(engine),
+            Err(error) => {
+                warn!(
+                    "The {} engine failed, trying the next: {}",
+                    engine.name(),
+                    error
+                );
+                // Whatever was copied before failing is started over
+                if target.exists() {
+                    set_readonly(&target, false)?;
+                    fs::remove_dir_all(&target)?;
+                }
+            }
+        }
+    }
+    Engine::Plain.copy(from, to)?;
+    fault::check("copy")?;
+    Ok(Engine::Plain)
+}
+
+// Moves everything below `from` into `to`, replacing files which are there
+// already
+fn move_into(from: &Path, to: &Path) -> Result<(), CliError> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() && target.is_dir() {
+            move_into(&entry.path(), &target)?;
+        } else {
+            fs::rename(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+fn first_file(dir: &Path) -> Option<PathBuf> {
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_file() {
+            return Some(path);
+        }
+        if let Some(file) = path.is_dir().then(|| first_file(&path)).flatten() {
+            return Some(file);
+        }
+    }
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(from: &Path, to: &Path) -> Result<(), CliError> {
+    cp(&["-R", "--reflink=always"], from, to)
+}
+
+#[cfg(target_os = "macos")]
+fn reflink(from: &Path, to: &Path) -> Result<(), CliError> {
+    cp(&["-R", "-c"], from, to)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink(_: &Path, _: &Path) -> Result<(), CliError> {
+    Err(CliError::Io(Error::other(
+        "reflinks are only supported on Linux and macOS",
+    )))
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn cp(args: &[&str], from: &Path, to: &Path) -> Result<(), CliError> {
+    let output = Command::new("cp").args(args).arg(from).arg(to).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(CliError::Io(Error::other(format!(
+            "cp failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))))
+    }
+}
+
+fn hard_link_dir(from: &Path, to: &Path, linked: &mut usize) -> Result<(), CliError> {
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            hard_link_dir(&entry.path(), &target, linked)?;
+        } else {
+            fs::hard_link(entry.path(), target)?;
+            *linked += 1;
+            fault::check_partway("copy", *linked)?;
+        }
+    }
+    Ok(())
+}
+
+// Creates the directories up front, then copies the files on a few threads
+fn parallel_copy(from: &Path, to: &Path) -> Result<(), CliError> {
+    let mut files = Vec::new();
+    create_dirs(from, to, &mut files)?;
+    let total_files = files.len();
+    let total_bytes = files.iter().map(|(_, _, size)| size).sum();
+
+    let copied_files = AtomicUsize::new(0);
+    let copied_bytes = AtomicU64::new(0);
+    for_each_parallel(&files, |(source, target, size)| {
+        fs::copy(source, target)?;
+        let copied = copied_files.fetch_add(1, Ordering::Relaxed) + 1;
+        progress::report(
+            "copy",
+            &progress::Update {
+                files: copied,
+                total_files,
+                bytes: copied_bytes.fetch_add(*size, Ordering::Relaxed) + size,
+                total_bytes,
+                file: source,
+            },
+        );
+        fault::check_partway("copy", copied)
+    })?;
+    progress::finish("copy");
+    Ok(())
+}
+
+/// Runs the action on every item, spread over a few threads, stopping at the
+/// first error.
+pub fn for_each_parallel<T, F>(items: &[T], action: F) -> Result<(), CliError>
+where
+    T: Sync,
+    F: Fn(&T) -> Result<(), CliError> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(8);
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| -> Result<(), CliError> {
+                    while let Some(item) = items.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        if failed.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        if let Err(error) = action(item) {
+                            failed.store(true, Ordering::Relaxed);
+                            return Err(error);
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("A copying thread panicked"))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect()
+    })
+}
+
+/// Creates the directory `to` and every directory below `from` in it,
+/// collecting the files to copy along with their sizes.
+pub fn create_dirs(
+    from: &Path,
+    to: &Path,
+    files: &mut Vec<(PathBuf, PathBuf, u64)>,
+) -> Result<(), CliError> {
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            create_dirs(&entry.path(), &target, files)?;
+        } else {
+            files.push((entry.path(), target, entry.metadata()?.len()));
+        }
+    }
+    Ok(())
+}
+
+fn plain_copy(from: &Path, to: &Path) -> Result<(), CliError> {
+    let options = dir::CopyOptions::new();
+    // fs_extra counts the size of directories as well, which are not copied
+    let content = dir::get_dir_content(from)?;
+    let total_files = content.files.len();
+    let mut total_bytes = 0;
+    for file in &content.files {
+        total_bytes += fs::metadata(file)?.len();
+    }
+    let mut files = 0;
+    let mut current = String::new();
+    let mut failed = None;
+    dir::copy_with_progress(from, to, &options, |transit| {
+        if transit.file_name != current {
+            // Every file before this one is done
+            if let Err(error) = fault::check_partway("copy", files) {
+                failed = Some(error);
+                return dir::TransitProcessResult::Abort;
+            }
+            current = transit.file_name.clone();
+            files += 1;
+        }
+        progress::report(
+            "copy",
+            &progress::Update {
+                files,
+                total_files,
+                bytes: transit.copied_bytes,
+                total_bytes,
+                file: Path::new(&transit.file_name),
+            },
+        );
+        dir::TransitProcessResult::ContinueOrAbort
+    })?;
+    progress::finish("copy");
+    match failed {
+        Some(error) => Err(error),
+        None => fault::check_partway("copy", files),
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct MirrorStats {
+    pub copied: usize,
+    pub skipped: usize,
+    pub removed: usize,
+}
+
+/// Makes `to` an exact copy of `from`, only copying files that differ.
+/// Files with the same size and modification time are assumed unchanged;
+/// files with the same size but another modification time are compared by
+/// hash. Anything in `to` which is not present in `from` is removed. The
+/// files are compared and copied on a few threads, reporting progress as
+/// `operation`.
+pub fn mirror_dir(operation: &str, from: &Path, to: &Path) -> Result<MirrorStats, CliError> {
+    let mut files = Vec::new();
+    let mut removed = 0;
+    mirror_dirs(from, to, &mut files, &mut removed)?;
+    // Skipped files count towards the progress as well
+    let total_files = files.len();
+    let total_bytes = files.iter().map(|(_, _, size)| size).sum();
+
+    let copied = AtomicUsize::new(0);
+    let skipped = AtomicUsize::new(0);
+    let done_files = AtomicUsize::new(0);
+    let done_bytes = AtomicU64::new(0);
+    for_each_parallel(&files, |(source, target, size)| {
+        if is_unchanged(source, target)? {
+            skipped.fetch_add(1, Ordering::Relaxed);
+        } else {
+            if target.is_dir() {
+                fs::remove_dir_all(target)?;
+            }
+            fs::copy(source, target)?;
+            copy_modified_time(source, target)?;
+            copied.fetch_add(1, Ordering::Relaxed);
+        }
+        let done = done_files.fetch_add(1, Ordering::Relaxed) + 1;
+        progress::report(
+            operation,
+            &progress::Update {
+                files: done,
+                total_files,
+                bytes: done_bytes.fetch_add(*size, Ordering::Relaxed) + size,
+                total_bytes,
+                file: source,
+            },
+        );
+        fault::check_partway(operation, done)
+    })?;
+    progress::finish(operation);
+    Ok(MirrorStats {
+        copied: copied.into_inner(),
+        skipped: skipped.into_inner(),
+        removed,
+    })
+}
+
+// Creates the directories of `from` in `to` and removes whatever `from` does
+// not have, collecting the files to compare
+fn mirror_dirs(
+    from: &Path,
+    to: &Path,
+    files: &mut Vec<(PathBuf, PathBuf, u64)>,
+    removed: &mut usize,
+) -> Result<(), CliError> {
+    if to.is_file() {
+        fs::remove_file(to)?;
+    }
+    fs::create_dir_all(to)?;
+
+    let mut seen = HashSet::new();
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        seen.insert(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            mirror_dirs(&entry.path(), &target, files, removed)?;
+        } else {
+            files.push((entry.path(), target, entry.metadata()?.len()));
+        }
+    }
+
+    for entry in fs::read_dir(to)? {
+        let entry = entry?;
+        if seen.contains(&entry.file_name()) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+        *removed += 1;
+    }
+    Ok(())
+}
+
+fn is_unchanged(source: &Path, target: &Path) -> Result<bool, CliError> {
+    let target_metadata = match fs::metadata(target) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return Ok(false),
+    };
+    let source_metadata = fs::metadata(source)?;
+    if source_metadata.len() != target_metadata.len() {
+        return Ok(false);
+    }
+    if source_metadata.modified()? == target_metadata.modified()? {
+        return Ok(true);
+    }
+    let unchanged = manifest::hash(source)? == manifest::hash(target)?;
+    if unchanged {
+        copy_modified_time(source, target)?;
+    }
+    Ok(unchanged)
+}
+
+fn copy_modified_time(source: &Path, target: &Path) -> Result<(), CliError> {
+    let modified = fs::metadata(source)?.modified()?;
+    File::options()
+        .write(true)
+        .open(target)?
+        .set_modified(modified)?;
+    Ok(())
+}
+
+/// Recursively marks a directory and everything in it as read-only, or
+/// makes it writable again. Directories are made writable before their
+/// contents and read-only after them, so the walk itself never gets stuck.
+pub fn set_readonly(path: &Path, readonly: bool) -> Result<(), CliError> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        if !readonly {
+            set_writable(path, true)?;
+        }
+        for entry in fs::read_dir(path)? {
+            set_readonly(&entry?.path(), readonly)?;
+        }
+        if readonly {
+            set_writable(path, false)?;
+        }
+    } else if metadata.is_file() {
+        set_writable(path, !readonly)?;
+    }
+    Ok(())
+}
+
+/// Like [`set_readonly`], but leaves the files alone, for directories holding
+/// hard links to files which other directories link to as well.
+pub fn set_dirs_readonly(path: &Path, readonly: bool) -> Result<(), CliError> {
+    if !readonly {
+        set_writable(path, true)?;
+    }
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            set_dirs_readonly(&entry.path(), readonly)?;
+        }
+    }
+    if readonly {
+        set_writable(path, false)?;
+    }
+    Ok(())
+}
+
+fn set_writable(path: &Path, writable: bool) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = permissions.mode();
+        permissions.set_mode(if writable {
+            mode | 0o200
+        } else {
+            mode & !0o222
+        });
+    }
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(!writable);
+    fs::set_permissions(path, permissions)
+}
@@ -0,0 +1,82 @@
+use std::io::Error;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+use crate::CliError;
+
+/// The volume a path is stored on, along with how much space is free on it.
+pub struct Volume {
+    pub name: String,
+    pub free: u64,
+}
+
+/// The volume the given path is stored on. The path does not need to exist,
+/// the volume of its nearest existing ancestor is used in that case.
+pub fn volume(path: &Path) -> Result<Volume, CliError> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(path);
+    query(existing)
+}
+
+#[cfg(windows)]
+fn query(path: &Path) -> Result<Volume, CliError> {
+    let script = format!(
+        "$drive = (Get-Item -LiteralPath '{}').PSDrive; \"$($drive.Name) $($drive.Free)\"",
+        path.display().to_string().replace('\'', "''")
+    );
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output()?;
+    if !output.status.success() {
+        return Err(failed("powershell", output.status));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut fields = stdout.split_whitespace();
+    match (
+        fields.next(),
+        fields.next().and_then(|free| free.parse().ok()),
+    ) {
+        (Some(name), Some(free)) => Ok(Volume {
+            name: format!("{}:", name),
+            free,
+        }),
+        _ => Err(unexpected(&stdout)),
+    }
+}
+
+#[cfg(not(windows))]
+fn query(path: &Path) -> Result<Volume, CliError> {
+    let output = Command::new("df").arg("-Pk").arg(path).output()?;
+    if !output.status.success() {
+        return Err(failed("df", output.status));
+    }
+    // Filesystem, 1024-blocks, Used, Available, Capacity and Mounted on, where
+    // only the mount point may contain spaces
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = stdout
+        .lines()
+        .nth(1)
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect();
+    match fields.get(3).and_then(|free| free.parse::<u64>().ok()) {
+        Some(free) if fields.len() > 5 => Ok(Volume {
+            name: fields[5..].join(" "),
+            free: free * 1024,
+        }),
+        _ => Err(unexpected(&stdout)),
+    }
+}
+
+fn failed(command: &str, status: ExitStatus) -> CliError {
+    CliError::Io(Error::other(format!("{} failed with {}", command, status)))
+}
+
+fn unexpected(output: &str) -> CliError {
+    CliError::Io(Error::other(format!(
+        "Unexpected free space output [{}]",
+        output.trim()
+    )))
+}
@@ -0,0 +1,81 @@
+use std::env;
+use std::io::Error;
+use std::process::Command;
+
+use crate::{nauttaja_dir, CliError};
+
+// Integrity level of processes running as administrator
+const HIGH_INTEGRITY_SID: &str = "S-1-16-12288";
+
+/// Whether the current command could be run again as administrator, which is
+/// only the case on Windows when not running as administrator already.
+pub fn available() -> bool {
+    cfg!(windows) && !is_elevated()
+}
+
+fn is_elevated() -> bool {
+    Command::new("whoami")
+        .arg("/groups")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(HIGH_INTEGRITY_SID))
+}
+
+/// Runs the current command again as administrator through UAC, in a new
+/// window, and waits for it to finish. The data directory is passed along,
+/// since the administrator may be another user with another home directory.
+pub fn relaunch() -> Result<(), CliError> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if !args.iter().any(|arg| arg == "--data-dir") {
+        args.push("--data-dir".to_string());
+        args.push(nauttaja_dir()?.display().to_string());
+    }
+    let args: Vec<String> = args.iter().map(|arg| quote(arg)).collect();
+    let script = format!(
+        "$process = Start-Process -FilePath {} -ArgumentList {} -Verb RunAs -Wait -PassThru; exit $process.ExitCode",
+        powershell_string(&env::current_exe()?.display().to_string()),
+        powershell_string(&args.join(" "))
+    );
+    let status = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(CliError::Io(Error::other(format!(
+            "nauttaja running as administrator failed with {}",
+            status
+        ))))
+    }
+}
+
+// Quotes an argument the way Windows programs split their command line
+fn quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+fn powershell_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::Duration;
+
+use fs_extra::dir;
+use log::{debug, warn};
+
+use crate::{human_size, nauttaja_dir, CliError};
+
+const NAUTTAJA_THROUGHPUT_FILE: &str = "throughput.json";
+
+/// Estimates are only told before operations expected to take at least this
+/// long, dry runs tell them regardless.
+pub const LONG: Duration = Duration::from_secs(5);
+
+// Shorter operations say more about the cost of starting one than about how
+// fast the disk is, so they are not measured
+const MIN_SAMPLE: Duration = Duration::from_millis(500);
+
+/// How much an operation has to get through.
+#[derive(Clone, Copy, Debug)]
+pub struct Work {
+    pub bytes: u64,
+    pub files: u64,
+}
+
+impl Work {
+    pub fn of_dir(dir: &Path) -> Result<Work, CliError> {
+        // Only files, like the sizes cached in the gamedb, since the size of
+        // the directory would count the directories themselves as well
+        let files = dir::get_dir_content(dir)?.files;
+        let mut bytes = 0;
+        for file in &files {
+            bytes += fs::metadata(file)?.len();
+        }
+        Ok(Work {
+            bytes,
+            files: files.len() as u64,
+        })
+    }
+}
+
+// Bytes per second measured for each operation, averaged with earlier
+// measurements so a single slow run does not throw the estimates off
+type Throughput = BTreeMap<String, f64>;
+
+fn read() -> Throughput {
+    let file = match nauttaja_dir() {
+        Ok(dir) => dir.join(NAUTTAJA_THROUGHPUT_FILE),
+        Err(_) => return Throughput::new(),
+    };
+    match fs::read_to_string(&file) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+            debug!("Ignoring [{}]: {}", file.display(), error);
+            Throughput::new()
+        }),
+        Err(error) if error.kind() == ErrorKind::NotFound => Throughput::new(),
+        Err(error) => {
+            debug!("Failed to read [{}]: {}", file.display(), error);
+            Throughput::new()
+        }
+    }
+}
+
+/// Records how long `operation` took to get through `work`. Estimates are
+/// only a nicety, so failing to record one is logged and otherwise ignored.
+pub fn record(operation: &str, work: Work, elapsed: Duration) {
+    if elapsed < MIN_SAMPLE || work.bytes == 0 {
+        return;
+    }
+    let measured = work.bytes as f64 / elapsed.as_secs_f64();
+    let mut throughput = read();
+    let rate = match throughput.get(operation) {
+        Some(rate) => (rate + measured) / 2.0,
+        None => measured,
+    };
+    debug!(
+        "Measured {}/s for [{}], estimating with {}/s",
+        human_size(measured as u64),
+        operation,
+        human_size(rate as u64)
+    );
+    throughput.insert(operation.to_string(), rate);
+    let result = nauttaja_dir().and_then(|dir| {
+        let content = serde_json::to_string_pretty(&throughput)?;
+        fs::write(dir.join(NAUTTAJA_THROUGHPUT_FILE), content)?;
+        Ok(())
+    });
+    if let Err(error) = result {
+        warn!(
+            "Failed to record the throughput of [{}]: {}",
+            operation, error
+        );
+    }
+}
+
+/// How long `operation` is expected to take to get through `work`, if it has
+/// been measured before.
+pub fn duration(operation: &str, work: Work) -> Option<Duration> {
+    read()
+        .get(operation)
+        .filter(|rate| **rate > 0.0)
+        .map(|rate| Duration::from_secs_f64(work.bytes as f64 / rate))
+}
+
+/// Whether `operation` is expected to take long enough to tell beforehand.
+pub fn is_long(operation: &str, work: Work) -> bool {
+    duration(operation, work).is_some_and(|duration| duration >= LONG)
+}
+
+/// Describes `work` like "about 1.2 GiB across 34k files, ~45s on this disk",
+/// leaving out the duration when `operation` has not been measured yet.
+pub fn describe(operation: &str, work: Work) -> String {
+    let mut description = format!(
+        "about {} across {} files",
+        human_size(work.bytes),
+        approximate(work.files)
+    );
+    if let Some(duration) = duration(operation, work) {
+        description.push_str(&format!(", ~{} on this disk", describe_duration(duration)));
+    }
+    description
+}
+
+fn approximate(count: u64) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=9_999 => format!("{:.1}k", count as f64 / 1_000.0),
+        10_000..=999_999 => format!("{}k", (count as f64 / 1_000.0).round()),
+        _ => format!("{:.1}M", count as f64 / 1_000_000.0),
+    }
+}
+
+fn describe_duration(duration: Duration) -> String {
+    let secs = duration.as_secs_f64().round().max(1.0) as u64;
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
@@ -0,0 +1,125 @@
+use std::io::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use log::warn;
+
+use crate::CliError;
+
+/// Points at which a failure can be injected with the hidden --inject-fault
+/// flag, so the paths which clean up after failures can be tried out:
+///
+/// - `copy`: every copy engine, and copying with dedup_saves, once everything
+///   has been copied, or partway with `after=<files>`
+/// - `backup`: the emergency backup, once it has been mirrored, or partway
+///   with `after=<files>`
+/// - `rollback`: rolling back a failed load, once the partially loaded save is
+///   gone
+/// - `gamedb`: writing the gamedb, before the new file replaces the old one
+/// - `delete`: deleting a save, before its gamedb entry is kept with its files
+///
+/// Each injected failure happens once, the first time its point is reached,
+/// so a point can be given more than once to fail more than once.
+pub const POINTS: &[&str] = &["copy", "backup", "rollback", "gamedb", "delete"];
+
+// Points reached for every file, which can fail partway
+const PARTWAY_POINTS: &[&str] = &["copy", "backup"];
+
+#[derive(Debug)]
+pub struct Fault {
+    point: String,
+    after: Option<usize>,
+    crash: bool,
+    happened: AtomicBool,
+}
+
+static INJECTED: OnceLock<Vec<Fault>> = OnceLock::new();
+
+/// Parses `<point>`, which fails with an error, or `<point>:crash`, which
+/// aborts the process on the spot as if it was killed. `copy` and `backup`
+/// take `:after=<files>` before that, failing once that many files are done.
+pub fn parse(fault: &str) -> Result<Fault, String> {
+    let mut parts = fault.split(':');
+    let point = parts.next().unwrap_or_default();
+    if !POINTS.contains(&point) {
+        return Err(format!(
+            "unknown point [{}], use one of {}",
+            point,
+            POINTS.join(", ")
+        ));
+    }
+    let (mut after, mut crash) = (None, false);
+    for part in parts {
+        match part.strip_prefix("after=") {
+            Some(files) if PARTWAY_POINTS.contains(&point) => {
+                after = Some(
+                    files
+                        .parse()
+                        .map_err(|_| format!("[{}] is not a number of files", files))?,
+                );
+            }
+            Some(_) => {
+                return Err(format!(
+                    "[{}] cannot fail partway, only {} can",
+                    point,
+                    PARTWAY_POINTS.join(" and ")
+                ))
+            }
+            None if part == "crash" => crash = true,
+            None => {
+                return Err(format!(
+                    "unknown option [{}], use after=<files> or crash",
+                    part
+                ))
+            }
+        }
+    }
+    Ok(Fault {
+        point: point.to_string(),
+        after,
+        crash,
+        happened: AtomicBool::new(false),
+    })
+}
+
+pub fn inject(faults: Vec<Fault>) {
+    INJECTED.set(faults).expect("Faults are only injected once");
+}
+
+/// Fails if a failure was injected at the point, or ends the process without
+/// cleaning anything up if a crash was.
+pub fn check(point: &str) -> Result<(), CliError> {
+    trigger(point, |after| after.is_none())
+}
+
+/// Like [`check`], for a point reached partway once `done` files are done.
+pub fn check_partway(point: &str, done: usize) -> Result<(), CliError> {
+    trigger(point, |after| after.is_some_and(|after| done >= after))
+}
+
+fn trigger<F>(point: &str, due: F) -> Result<(), CliError>
+where
+    F: Fn(Option<usize>) -> bool,
+{
+    let fault = INJECTED.get().and_then(|faults| {
+        faults.iter().find(|fault| {
+            fault.point == point
+                && due(fault.after)
+                && !fault.happened.swap(true, Ordering::Relaxed)
+        })
+    });
+    match fault {
+        Some(fault) if fault.crash => {
+            warn!("Crashing at [{}] as injected", point);
+            std::process::abort();
+        }
+        Some(_) => {
+            warn!("Failing at [{}] as injected", point);
+            Err(CliError::Io(Error::other(format!(
+                "failure injected at [{}]",
+                point
+            ))))
+        }
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
+
+use chrono::Local;
+use log::debug;
+use serde_json::value::RawValue;
+use serde_json::{json, Value};
+
+use crate::{
+    describe_json_error, fault, nauttaja_dir, parse_timestamp, CliError, GameDB, Save,
+    BACKUP_NAME_FORMAT, NAUTTAJA_GAMEDB_FILE,
+};
+
+const NAUTTAJA_GAMEDB_LOCK_FILE: &str = "gamedb.lock";
+const NAUTTAJA_GAMEDB_BACKUPS_DIRECTORY: &str = "gamedb-backups";
+const GAMEDB_BACKUP_RETENTION: usize = 4;
+
+const EXPECTED_SAVE: &str =
+    "every save needs a name, a directory and a timestamp like 2021-01-31 23:59:59";
+
+// The gamedb as last read or written by this process, along with the size and
+// modification time of its file at that point. Commands tend to load the
+// gamedb several times over, which then only parses it once.
+static CACHE: Mutex<Option<Cached>> = Mutex::new(None);
+
+struct Cached {
+    file: PathBuf,
+    stamp: (u64, SystemTime),
+    gamedb: GameDB,
+}
+
+fn stamp(file: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = fs::metadata(file).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+fn cache(file: &Path, gamedb: Option<&GameDB>) {
+    let cached = gamedb.and_then(|gamedb| {
+        Some(Cached {
+            file: file.to_path_buf(),
+            stamp: stamp(file)?,
+            gamedb: gamedb.clone(),
+        })
+    });
+    *CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = cached;
+}
+
+struct Rejected<'a> {
+    list: &'static str,
+    entry: Option<&'a RawValue>,
+    problem: String,
+}
+
+/// Parses the gamedb, checking every save on its own. Well-formed saves are
+/// kept, while everything else is rejected along with a description of what
+/// is wrong and where.
+fn parse(data: &str) -> (GameDB, Vec<Rejected<'_>>) {
+    let mut gamedb = GameDB::default();
+    let mut rejected = Vec::new();
+
+    let document: BTreeMap<&str, &RawValue> = match serde_json::from_str(data) {
+        Ok(document) => document,
+        Err(error) => {
+            rejected.push(Rejected {
+                list: "gamedb",
+                entry: None,
+                problem: format!(
+                    "{}, expected an object with the lists \"saves\" and \"trash\"",
+                    describe_json_error(&error, (1, 1))
+                ),
+            });
+            return (gamedb, rejected);
+        }
+    };
+
+    for list in &["saves", "trash"] {
+        let raw = match document.get(list) {
+            Some(raw) => raw,
+            None => {
+                rejected.push(Rejected {
+                    list,
+                    entry: None,
+                    problem: format!("the list \"{}\" is missing", list),
+                });
+                continue;
+            }
+        };
+        let entries: Vec<&RawValue> = match serde_json::from_str(raw.get()) {
+            Ok(entries) => entries,
+            Err(error) => {
+                rejected.push(Rejected {
+                    list,
+                    entry: Some(raw),
+                    problem: format!(
+                        "{}, expected \"{}\" to be a list of saves",
+                        describe_json_error(&error, position_of(data, raw.get())),
+                        list
+                    ),
+                });
+                continue;
+            }
+        };
+
+        // Positions are only worked out for rejected saves, since finding them
+        // means counting the lines up to each save
+        for (index, entry) in entries.into_iter().enumerate() {
+            let problem = match serde_json::from_str::<Save>(entry.get()) {
+                Ok(save) => match check_save(&save) {
+                    None => {
+                        match *list {
+                            "saves" => gamedb.saves.push(save),
+                            _ => gamedb.trash.push(save),
+                        }
+                        continue;
+                    }
+                    Some(problem) => {
+                        let line = position_of(data, entry.get()).0;
+                        format!("{}[{}] at line {}: {}", list, index, line, problem)
+                    }
+                },
+                Err(error) => format!(
+                    "{}[{}] at {} ({})",
+                    list,
+                    index,
+                    describe_json_error(&error, position_of(data, entry.get())),
+                    EXPECTED_SAVE
+                ),
+            };
+            rejected.push(Rejected {
+                list,
+                entry: Some(entry),
+                problem,
+            });
+        }
+    }
+
+    for (key, raw) in &document {
+        match *key {
+            "saves" | "trash" | "loaded" | "sessions" => {}
+            "version" => gamedb.version = serde_json::from_str(raw.get()).ok(),
+            // Kept for the newer version of nauttaja which added it
+            _ => {
+                if let Ok(value) = serde_json::from_str(raw.get()) {
+                    gamedb.extra.insert(key.to_string(), value);
+                }
+            }
+        }
+    }
+
+    if let Some(raw) = document.get("loaded") {
+        match serde_json::from_str(raw.get()) {
+            Ok(loaded) => gamedb.loaded = Some(loaded),
+            Err(error) => rejected.push(Rejected {
+                list: "loaded",
+                entry: Some(raw),
+                problem: format!(
+                    "loaded at {} (expected the directory, timestamp and digest of the loaded save)",
+                    describe_json_error(&error, position_of(data, raw.get()))
+                ),
+            }),
+        }
+    }
+
+    if let Some(raw) = document.get("sessions") {
+        match serde_json::from_str(raw.get()) {
+            Ok(sessions) => gamedb.sessions = sessions,
+            Err(error) => rejected.push(Rejected {
+                list: "sessions",
+                entry: Some(raw),
+                problem: format!(
+                    "sessions at {} (expected a list of sessions, each with when it started and ended)",
+                    describe_json_error(&error, position_of(data, raw.get()))
+                ),
+            }),
+        }
+    }
+
+    let mut names = HashSet::new();
+    let mut duplicates = Vec::new();
+    gamedb.saves.retain(|save| {
+        let unique = names.insert(save.name.clone());
+        if !unique {
+            duplicates.push(format!(
+                "saves: the name [{}] is used by more than one save, only the first is kept",
+                save.name
+            ));
+        }
+        unique
+    });
+    rejected.extend(duplicates.into_iter().map(|problem| Rejected {
+        list: "saves",
+        entry: None,
+        problem,
+    }));
+
+    (gamedb, rejected)
+}
+
+fn check_save(save: &Save) -> Option<String> {
+    if save.name.is_empty() {
+        return Some("the name is empty".to_string());
+    }
+    let mut components = Path::new(&save.directory).components();
+    let single_directory = matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    );
+    if !single_directory {
+        return Some(format!(
+            "[{}] has the directory [{}], expected the name of a single directory in the saves directory",
+            save.name, save.directory
+        ));
+    }
+    if parse_timestamp(&save.timestamp).is_none() {
+        return Some(format!(
+            "[{}] has the timestamp [{}], expected a timestamp like 2021-01-31 23:59:59",
+            save.name, save.timestamp
+        ));
+    }
+    if let Some(removed) = &save.removed {
+        if parse_timestamp(removed).is_none() {
+            return Some(format!(
+                "[{}] was removed at [{}], expected a timestamp like 2021-01-31 23:59:59",
+                save.name, removed
+            ));
+        }
+    }
+    None
+}
+
+// Line and column where a slice of the document starts
+fn position_of(data: &str, part: &str) -> (usize, usize) {
+    let offset = part.as_ptr() as usize - data.as_ptr() as usize;
+    let before = &data[..offset];
+    let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+    (before.matches('\n').count() + 1, offset - line_start + 1)
+}
+
+/// Describes everything which is wrong with the gamedb, nothing if it is fine.
+pub fn problems(file: &Path) -> Result<Vec<String>, CliError> {
+    let data = fs::read_to_string(file)?;
+    let (_, rejected) = parse(&data);
+    Ok(rejected
+        .into_iter()
+        .map(|rejected| rejected.problem)
+        .collect())
+}
+
+pub fn load(file: &Path) -> Result<GameDB, CliError> {
+    if let Some(cached) = CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .as_ref()
+        .filter(|cached| cached.file == file && stamp(file) == Some(cached.stamp))
+    {
+        return Ok(cached.gamedb.clone());
+    }
+    let started = Instant::now();
+    let data = fs::read_to_string(file)?;
+    let (gamedb, rejected) = parse(&data);
+    debug!(
+        "Read [{}] with {} saves in {:.2?}",
+        file.display(),
+        gamedb.saves.len() + gamedb.trash.len(),
+        started.elapsed()
+    );
+    if rejected.is_empty() {
+        cache(file, Some(&gamedb));
+        return Ok(gamedb);
+    }
+    let mut report = format!("Invalid gamedb [{}]", file.display());
+    for rejected in &rejected {
+        report.push_str(&format!("\n  {}", rejected.problem));
+    }
+    report.push_str(
+        "\nRun the command again with --repair to keep the well-formed saves and quarantine the rest,",
+    );
+    report.push_str(
+        "\nor run nauttaja recover to be guided through restoring a backup of the gamedb",
+    );
+    Err(CliError::GameDB(report))
+}
+
+/// Takes the exclusive right to write the gamedb, waiting for any other
+/// writer to finish first. The lock is released when the file is dropped,
+/// including when the process exits unexpectedly.
+pub fn lock(dir: &Path) -> Result<File, CliError> {
+    fs::create_dir_all(dir)?;
+    let lock = File::create(dir.join(NAUTTAJA_GAMEDB_LOCK_FILE))?;
+    lock.lock()?;
+    Ok(lock)
+}
+
+/// Replaces the gamedb in one step, so readers never see a partially written
+/// file.
+pub fn write(file: &Path, gamedb: &GameDB) -> Result<(), CliError> {
+    let temporary = file.with_extension("json.tmp");
+    fs::write(&temporary, serde_json::to_string_pretty(gamedb)?)?;
+    fault::check("gamedb")?;
+    fs::rename(temporary, file)?;
+    debug!("Wrote [{}]", file.display());
+    cache(file, Some(gamedb));
+    Ok(())
+}
+
+/// Copies the gamedb into `gamedb-backups`, keeping only the latest few copies.
+pub fn backup() -> Result<Option<PathBuf>, CliError> {
+    let dir = nauttaja_dir()?;
+    let file = dir.join(NAUTTAJA_GAMEDB_FILE);
+    if !file.exists() {
+        return Ok(None);
+    }
+    let backups_dir = dir.join(NAUTTAJA_GAMEDB_BACKUPS_DIRECTORY);
+    fs::create_dir_all(&backups_dir)?;
+    let backup = backups_dir.join(format!(
+        "gamedb-{}.json",
+        Local::now().format(BACKUP_NAME_FORMAT)
+    ));
+    {
+        let _lock = lock(&dir)?;
+        fs::copy(&file, &backup)?;
+    }
+
+    let backups = backups()?;
+    let excess = backups.len().saturating_sub(GAMEDB_BACKUP_RETENTION);
+    for old in &backups[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(Some(backup))
+}
+
+/// Every copy of the gamedb made by `backup`, oldest first.
+pub fn backups() -> Result<Vec<PathBuf>, CliError> {
+    let backups_dir = nauttaja_dir()?.join(NAUTTAJA_GAMEDB_BACKUPS_DIRECTORY);
+    if !backups_dir.exists() {
+        return Ok(Vec::new());
+    }
+    // Timestamped names sort oldest first
+    let mut backups: Vec<PathBuf> = fs::read_dir(&backups_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    backups.sort();
+    Ok(backups)
+}
+
+/// Replaces the gamedb with a backup. The replaced gamedb is kept next to it,
+/// and its path is returned.
+pub fn restore(backup: &Path) -> Result<Option<PathBuf>, CliError> {
+    let dir = nauttaja_dir()?;
+    let file = dir.join(NAUTTAJA_GAMEDB_FILE);
+    let _lock = lock(&dir)?;
+    let replaced = if file.exists() {
+        let replaced = dir.join(format!(
+            "gamedb-replaced-{}.json",
+            Local::now().format(BACKUP_NAME_FORMAT)
+        ));
+        fs::copy(&file, &replaced)?;
+        Some(replaced)
+    } else {
+        None
+    };
+    let temporary = file.with_extension("json.tmp");
+    fs::copy(backup, &temporary)?;
+    fs::rename(&temporary, &file)?;
+    cache(&file, None);
+    Ok(replaced)
+}
+
+/// Keeps the well-formed saves of a malformed gamedb and moves everything else,
+/// along with a copy of the original file, into a quarantine file next to it.
+pub fn repair() -> Result<(), CliError> {
+    let dir = nauttaja_dir()?;
+    let file = dir.join(NAUTTAJA_GAMEDB_FILE);
+    if !file.exists() {
+        return Ok(());
+    }
+    let _lock = lock(&dir)?;
+    let data = fs::read_to_string(&file)?;
+    let (gamedb, rejected) = parse(&data);
+    if rejected.is_empty() {
+        return Ok(());
+    }
+
+    let quarantine_file = dir.join(format!(
+        "gamedb-quarantine-{}.json",
+        Local::now().format(BACKUP_NAME_FORMAT)
+    ));
+    let quarantine = json!({
+        "original": data,
+        "rejected": rejected
+            .iter()
+            .map(|rejected| {
+                json!({
+                    "list": rejected.list,
+                    "problem": rejected.problem,
+                    "entry": rejected
+                        .entry
+                        .and_then(|entry| serde_json::from_str::<Value>(entry.get()).ok()),
+                })
+            })
+            .collect::<Vec<_>>(),
+    });
+    fs::write(&quarantine_file, serde_json::to_string_pretty(&quarantine)?)?;
+    write(&file, &gamedb)?;
+
+    for rejected in &rejected {
+        say!("Quarantined: {}", rejected.problem);
+    }
+    say!(
+        "Kept {} saves and {} removed saves, the rest was moved to [{}]",
+        gamedb.saves.len(),
+        gamedb.trash.len(),
+        quarantine_file.display()
+    );
+    say!("The directories of quarantined saves are left untouched");
+    Ok(())
+}
@@ -1,33 +1,142 @@
+use std::collections::BTreeMap;
 use std::env;
+use std::fmt;
 use std::fs;
-use std::io::{Error, ErrorKind};
+use std::io::{self, Error, ErrorKind, IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
-use chrono::{Datelike, Local, Timelike};
-use clap::{crate_version, App, Arg};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, Timelike};
+use clap::{crate_version, App, AppSettings, Arg, ArgMatches, ArgSettings};
 use fs_extra::dir;
+use log::info;
+use ratatui::crossterm::style::Stylize;
+use regex::{Regex, RegexBuilder};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 use uuid::Uuid;
 
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// Messages meant for people, moved to stderr with --json so stdout stays parseable
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if crate::output::is_quiet() {
+        } else if crate::output::is_json() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+// Results of commands, which are written to stdout even with --quiet, since
+// they are what was asked for
+macro_rules! show {
+    ($($arg:tt)*) => {
+        println!($($arg)*)
+    };
+}
+
+mod compress;
+mod config;
+mod copy;
+mod disk;
+mod elevation;
+mod estimate;
+mod fault;
+mod gamedb;
+mod manifest;
+mod noita;
+mod notify;
+mod output;
+mod pending_delete;
+mod process;
+mod progress;
+mod recover;
+mod sandbox;
+mod shell_integration;
+mod steam_cloud;
+mod store;
+mod tui;
+
+use config::{BackupPolicy, Config, Ellipsis, SuspiciousSavePolicy};
 
 const NOITA_SAVE_DIRECTORY: &str = "save00";
+const NOITA_WORLD_DIRECTORY: &str = "world";
+const NOITA_RUN_FILES: &[&str] = &["player.xml", "world_state.xml"];
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const BACKUP_NAME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+const SETTLE_TIME_SECS: u64 = 5;
 
 const NAUTTAJA_DIRECTORY: &str = ".nauttaja";
 const NAUTTAJA_SAVES_DIRECTORY: &str = "saves";
 const NAUTTAJA_LAST_REPLACED_DIRECTORY: &str = "backup";
+const NAUTTAJA_BENCH_DIRECTORY: &str = "bench";
 const NAUTTAJA_GAMEDB_FILE: &str = "gamedb.json";
+const NAUTTAJA_MAINTENANCE_FILE: &str = "maintenance.json";
+const NAUTTAJA_CURRENT_SAVE_LOCK_FILE: &str = "current-save.lock";
 
-#[derive(Serialize, Deserialize, Default, Debug, Clone)]
-struct Config {
-    noita_root_dir: String,
+// Commands which only read the data directory, besides listing the trash
+const READ_ONLY_COMMANDS: &[&str] = &[
+    "list", "info", "search", "which", "journal", "diff", "verify", "scrub",
+];
+
+// Commands which tell what they would do with --dry-run, besides the ones
+// which only read the data directory
+const DRY_RUN_COMMANDS: &[&str] = &[
+    "save",
+    "qs",
+    "load",
+    "ql",
+    "rollback",
+    "remove",
+    "prune",
+    "restore",
+    "delete",
+    "empty-trash",
+    "trash",
+];
+
+// Commands which never touch the gamedb, so it is neither checked nor kept
+// tidy before they run
+const GAMEDB_FREE_COMMANDS: &[&str] = &["open", "bench", "config"];
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+fn read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed)
+}
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
 struct GameDB {
     saves: Vec<Save>,
     trash: Vec<Save>,
-    config: Config,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    loaded: Option<Loaded>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sessions: Vec<Session>,
+    // Version of nauttaja which last wrote the gamedb
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    // Whatever newer versions of nauttaja store, kept as it is
+    #[serde(flatten)]
+    extra: BTreeMap<String, Value>,
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
@@ -35,12 +144,217 @@ struct Save {
     name: String,
     directory: String,
     timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    removed: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    files: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    health: Option<Health>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    verified: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    slot: Option<u32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    autosave: bool,
+    // Whether the files of the save are shared through the store
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    stored: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index: Option<noita::SaveIndex>,
+    // Version of nauttaja which created the save
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_by: Option<String>,
+    // Root directory of the Noita install the save was made from, unknown for
+    // imported saves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    noita_root_dir: Option<String>,
+    // How many times the save has been loaded, and when it last was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    loads: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_loaded: Option<String>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, Value>,
+}
+
+// The outcome of the latest verification of a save
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+enum Health {
+    Ok,
+    MissingFiles,
+    HashMismatch,
+    NoManifest,
+    Error,
+}
+
+impl Health {
+    fn describe(&self) -> &'static str {
+        match self {
+            Health::Ok => "OK",
+            Health::MissingFiles => "missing files",
+            Health::HashMismatch => "hash mismatch",
+            Health::NoManifest => "no manifest",
+            Health::Error => "unreadable",
+        }
+    }
+
+    // Saves without a manifest cannot be verified, but are not broken either
+    fn is_unhealthy(&self) -> bool {
+        !matches!(self, Health::Ok | Health::NoManifest)
+    }
+}
+
+// A stretch of Noita running, from when watch saw it start until it exited,
+// along with where the run was left
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+struct Session {
+    started: String,
+    ended: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seed: Option<String>,
+    // Time played according to Noita, which leaves out time spent in menus
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    playtime_secs: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    progress: Option<noita::RunProgress>,
+    // Saves made while Noita was running, by any command
+    #[serde(default)]
+    snapshots: usize,
+}
+
+// The save which the current save00 was last loaded from, or saved as
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+struct Loaded {
+    directory: String,
+    timestamp: String,
+    digest: String,
 }
 
 fn main() {
+    let restore = App::new("restore")
+        .about("Restores the specified saves")
+        .arg(
+            Arg::new("name")
+                .about("Names of the saves to restore")
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("all")
+                .long("all")
+                .about("Restore every removed save"),
+        )
+        .arg(
+            Arg::new("interactive")
+                .long("interactive")
+                .conflicts_with("name")
+                .about("Pick the saves to restore from a numbered list"),
+        );
+    let delete = App::new("delete")
+        .about("Permanently deletes the specified saves")
+        .arg(
+            Arg::new("name")
+                .about("Names of the saves to permanently delete")
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("interactive")
+                .long("interactive")
+                .conflicts_with("name")
+                .about("Pick the saves to permanently delete from a numbered list"),
+        );
+
     let mut app = App::new("nauttaja")
         .version(crate_version!())
         .long_version(crate_version!())
+        .arg(
+            Arg::new("sandbox")
+                .long("sandbox")
+                .global(true)
+                .about("Try nauttaja on a fake Noita install, leaving real saves untouched"),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .global(true)
+                .takes_value(true)
+                .possible_values(&["bar", "json", "none"])
+                .about("How to report progress on stderr, by default a bar on terminals, JSON with --json and nothing otherwise"),
+        )
+        .arg(
+            Arg::new("data-dir")
+                .long("data-dir")
+                .global(true)
+                .takes_value(true)
+                .about("Use another data directory than ~/.nauttaja, such as a copy from another machine"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .global(true)
+                .multiple_occurrences(true)
+                .conflicts_with("quiet")
+                .about("Log which paths are touched and how long it takes, twice to log how"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .global(true)
+                .about("Show nothing but prompts and results, for use in scripts"),
+        )
+        .arg(
+            Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .global(true)
+                .about("Answer yes to every confirmation, for use in scripts"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .global(true)
+                .about("Show which directories would be copied or removed and how the gamedb would change, without doing it"),
+        )
+        .arg(
+            Arg::new("read-only")
+                .long("read-only")
+                .global(true)
+                .about("Never modify the data directory, only commands which inspect saves are available"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .global(true)
+                .about("Write results to stdout as JSON, and messages to stderr"),
+        )
+        .arg(
+            Arg::new("no-color")
+                .long("no-color")
+                .global(true)
+                .about("Never color the output, which is also what setting NO_COLOR does"),
+        )
+        .arg(
+            Arg::new("inject-fault")
+                .long("inject-fault")
+                .global(true)
+                .hidden(true)
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .validator(fault::parse)
+                .about("Fail, or crash with <point>:crash, at a point of a storage operation"),
+        )
+        .arg(
+            Arg::new("repair")
+                .long("repair")
+                .global(true)
+                .about("Salvage a malformed gamedb or import, quarantining what is broken"),
+        )
         .subcommand(
             App::new("open")
                 .about("Open nauttajas root directory in explorer")
@@ -57,40 +371,256 @@ fn main() {
                 .about("Save the current game, with an optional custom name")
                 .arg(
                     Arg::new("name")
-                        .about("Name of the new save")
-                        .required(true),
+                        .about("Name of the new save, generated from the name template if left out"),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .about("Save even if an identical save already exists"),
+                )
+                .arg(
+                    Arg::new("overwrite")
+                        .long("overwrite")
+                        .about("Replace the save if one with the same name already exists"),
+                )
+                .arg(
+                    Arg::new("verify")
+                        .long("verify")
+                        .about("Re-hash the copied files and compare them against the current save"),
+                )
+                .arg(
+                    Arg::new("note")
+                        .long("note")
+                        .takes_value(true)
+                        .about("A description of the save, shown in listings"),
                 ),
         )
         .subcommand(
             App::new("load")
                 .about("Replace the current save with another saved game")
-                .arg(Arg::new("name").about("Name of the save to load")),
+                .arg(Arg::new("name").about("Name of the save to load"))
+                .arg(
+                    Arg::new("no-backup")
+                        .long("no-backup")
+                        .about("Skip the emergency backup of the current save"),
+                )
+                .arg(
+                    Arg::new("verify")
+                        .long("verify")
+                        .about("Re-hash the loaded files and compare them against the save"),
+                )
+                .arg(
+                    Arg::new("cross-profile")
+                        .long("cross-profile")
+                        .about("Load the save even if it was made from another Noita install"),
+                ),
+        )
+        .subcommand(
+            App::new("autosave")
+                .about("Keeps running, saving the current game at a regular interval")
+                .arg(
+                    Arg::new("every")
+                        .long("every")
+                        .takes_value(true)
+                        .default_value("10m")
+                        .validator(parse_interval)
+                        .about("How often to save, such as 30s, 10m or 1h"),
+                )
+                .arg(
+                    Arg::new("keep")
+                        .long("keep")
+                        .takes_value(true)
+                        .default_value("10")
+                        .validator(|keep| keep.parse::<usize>())
+                        .about("Number of autosaves to keep, older ones are deleted"),
+                ),
+        )
+        .subcommand(
+            App::new("watch")
+                .about("Keeps running, saving the current game every time Noita exits"),
+        )
+        .subcommand(
+            App::new("checkpoint")
+                .about("Keeps the latest autosave as a named save, which is no longer rotated")
+                .arg(
+                    Arg::new("label")
+                        .about("Name of the kept save")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            App::new("qs")
+                .about("Quicksaves the current game into a numbered slot, replacing what it held")
+                .arg(
+                    Arg::new("slot")
+                        .about("Number of the slot, 1 if left out")
+                        .validator(|slot| slot.parse::<u32>()),
+                ),
+        )
+        .subcommand(
+            App::new("ql")
+                .about("Loads the quicksave in a numbered slot")
+                .arg(
+                    Arg::new("slot")
+                        .about("Number of the slot, 1 if left out")
+                        .validator(|slot| slot.parse::<u32>()),
+                )
+                .arg(
+                    Arg::new("no-backup")
+                        .long("no-backup")
+                        .about("Skip the emergency backup of the current save"),
+                )
+                .arg(
+                    Arg::new("cross-profile")
+                        .long("cross-profile")
+                        .about("Load the save even if it was made from another Noita install"),
+                ),
         )
         .subcommand(
             App::new("list")
                 .about("Lists all currently saved games")
                 .arg(
-                    Arg::new("removed")
-                        .about("Lists all removed saves")
-                        .index(1)
-                        .possible_value("removed")
-                        .required(false),
+                    Arg::new("filter")
+                        .about("\"removed\" lists all removed saves, followed by an optional pattern to match names against")
+                        .value_name("removed|pattern")
+                        .multiple(true)
+                        .max_values(2),
+                )
+                .arg(
+                    Arg::new("size")
+                        .long("size")
+                        .about("Show the size and number of files of each save"),
+                )
+                .arg(
+                    Arg::new("details")
+                        .long("details")
+                        .about("Show how far the run in each save has progressed"),
+                )
+                .arg(
+                    Arg::new("sort")
+                        .long("sort")
+                        .takes_value(true)
+                        .possible_values(&["date", "name", "size", "last-loaded"])
+                        .about("Sort by date, newest first, by name, by size, largest first, or by when last loaded, most recently first"),
+                )
+                .arg(
+                    Arg::new("reverse")
+                        .long("reverse")
+                        .about("Reverse the order of the saves"),
+                )
+                .arg(
+                    Arg::new("min-orbs")
+                        .long("min-orbs")
+                        .takes_value(true)
+                        .validator(|orbs| orbs.parse::<usize>())
+                        .about("Only list saves whose run has found at least this many orbs"),
+                ),
+        )
+        .subcommand(
+            App::new("rollback")
+                .about("Loads an earlier save of the current run")
+                .arg(
+                    Arg::new("steps")
+                        .about("How many saves to step back, 1 if left out")
+                        .validator(|steps| steps.parse::<usize>()),
+                )
+                .arg(
+                    Arg::new("no-backup")
+                        .long("no-backup")
+                        .about("Skip the emergency backup"),
+                )
+                .arg(
+                    Arg::new("cross-profile")
+                        .long("cross-profile")
+                        .about("Load the save even if it was made from another Noita install"),
+                ),
+        )
+        .subcommand(
+            App::new("clone")
+                .about("Copies a save to a new save")
+                .arg(
+                    Arg::new("name")
+                        .about("Name of the save to copy")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("new")
+                        .about("Name of the new save")
+                        .required(true),
                 ),
         )
         .subcommand(
             App::new("remove")
-                .about("Remove the specified save")
-                .arg(Arg::new("name").about("Name of the save to remove")),
+                .about("Remove the specified saves")
+                .arg(
+                    Arg::new("name")
+                        .about("Names of the saves to remove")
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::new("interactive")
+                        .long("interactive")
+                        .conflicts_with("name")
+                        .about("Pick the saves to remove from a numbered list"),
+                ),
+        )
+        .subcommand(
+            App::new("prune")
+                .about("Moves old saves to the trash, keeping the latest and most recent ones")
+                .arg(
+                    Arg::new("keep-last")
+                        .long("keep-last")
+                        .takes_value(true)
+                        .validator(|keep| keep.parse::<usize>())
+                        .about("Number of latest saves to keep, prune_keep_last if left out"),
+                )
+                .arg(
+                    Arg::new("keep-days")
+                        .long("keep-days")
+                        .takes_value(true)
+                        .validator(|days| days.parse::<u32>())
+                        .about("Keep saves made within this many days, prune_keep_days if left out"),
+                ),
+        )
+        .subcommand(
+            App::new("rename")
+                .about("Renames a save, including removed ones")
+                .arg(
+                    Arg::new("old")
+                        .about("Current name of the save")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("new")
+                        .about("New name of the save")
+                        .required(true),
+                ),
         )
         .subcommand(
-            App::new("restore")
-                .about("Restores the specified save")
-                .arg(Arg::new("name").about("Name of the save to restore")),
+            App::new("note")
+                .about("Shows or changes the description of a save")
+                .arg(
+                    Arg::new("name")
+                        .about("Name of the save")
+                        .required(true),
+                )
+                .arg(Arg::new("text").about("New description of the save"))
+                .arg(
+                    Arg::new("clear")
+                        .long("clear")
+                        .about("Remove the description"),
+                ),
         )
+        .subcommand(restore.clone())
+        .subcommand(delete.clone())
+        .subcommand(App::new("empty-trash").about("Permanently deletes all removed saves"))
         .subcommand(
-            App::new("delete")
-                .about("Permanently deletes the specified save")
-                .arg(Arg::new("name").about("Name of the save to permanently delete")),
+            App::new("trash")
+                .about("Manage removed saves, listing them by default")
+                .subcommand(App::new("list").about("Lists all removed saves"))
+                .subcommand(restore)
+                .subcommand(delete)
+                .subcommand(App::new("empty").about("Permanently deletes all removed saves")),
         )
         .subcommand(
             App::new("import")
@@ -98,14 +628,195 @@ fn main() {
                 .arg(
                     Arg::new("name")
                         .about("Name of the new save")
-                        .required(true),
+                        .required_unless_present("from-nauttaja"),
                 )
                 .arg(
                     Arg::new("path")
                         .about("Path to the directory to import")
+                        .required_unless_present("from-nauttaja"),
+                )
+                .arg(
+                    Arg::new("from-nauttaja")
+                        .long("from-nauttaja")
+                        .value_name("path> <names")
+                        .min_values(1)
+                        .conflicts_with_all(&["name", "path"])
+                        .about("Import saves from another nauttaja data directory instead, all of them unless names are given"),
+                )
+                .arg(
+                    Arg::new("note")
+                        .long("note")
+                        .takes_value(true)
+                        .about("A description of the save, shown in listings"),
+                ),
+        )
+        .subcommand(
+            App::new("verify")
+                .about("Verifies the files of the specified save against its manifest")
+                .arg(Arg::new("name").about("Name of the save to verify"))
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .about("Verify all saves, including removed ones"),
+                )
+                .arg(
+                    Arg::new("summary")
+                        .long("summary")
+                        .about("Show one health status per save and an overall report"),
+                ),
+        )
+        .subcommand(App::new("scrub").about("Verifies every save, including removed ones"))
+        .subcommand(
+            App::new("maintain")
+                .about("Backs up the gamedb, purges expired deleted saves, indexes and verifies saves")
+                .arg(
+                    Arg::new("scrub")
+                        .long("scrub")
+                        .takes_value(true)
+                        .validator(|count| count.parse::<usize>())
+                        .about("How many of the least recently verified saves to verify, a quarter of all saves by default"),
+                ),
+        )
+        .subcommand(
+            App::new("recover").about(
+                "Guides you through recovering a malformed gamedb and saves missing from it",
+            ),
+        )
+        .subcommand(
+            App::new("compress")
+                .about("Compresses the world of saves made before compress_saves was enabled")
+                .arg(
+                    Arg::new("names")
+                        .about("Names of the saves to compress, every save when left out")
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            App::new("gc").about("Purge deleted saves which are still within their grace period"),
+        )
+        .subcommand(
+            App::new("info")
+                .about("Shows everything known about a save")
+                .arg(
+                    Arg::new("name")
+                        .about("Name of the save")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("inventory")
+                        .long("inventory")
+                        .about("List the wands, spells and perks of the player"),
+                ),
+        )
+        .subcommand(
+            App::new("diff")
+                .about("Shows the differences between two saves")
+                .arg(
+                    Arg::new("a")
+                        .about("Name of the earlier save")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("b")
+                        .about("Name of the later save")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("stats")
+                        .long("stats")
+                        .about("Compare the health, gold, perks and inventory of the player"),
+                ),
+        )
+        .subcommand(
+            App::new("journal")
+                .about("Writes the saves of a run, or every session seen by watch, as a Markdown journal")
+                .arg(
+                    Arg::new("run")
+                        .about("Seed of the run, or the name of one of its saves, every session if left out"),
+                ),
+        )
+        .subcommand(
+            App::new("search")
+                .about("Finds saves whose player has the specified spells or perks")
+                .arg(
+                    Arg::new("has")
+                        .long("has")
+                        .takes_value(true)
+                        .multiple(true)
+                        .required(true)
+                        .about("Id of a spell or perk, such as SUMMON_PORTAL"),
+                ),
+        )
+        .subcommand(
+            App::new("which")
+                .about("Shows which saves a name resolves to, and by which rule")
+                .arg(
+                    Arg::new("query")
+                        .about("Name, wildcard, slot number or pattern to resolve")
                         .required(true),
                 ),
         )
+        .subcommand(
+            App::new("index")
+                .about("Extracts run information from saves which have not been indexed yet")
+                .arg(
+                    Arg::new("rebuild")
+                        .long("rebuild")
+                        .about("Index every save again"),
+                ),
+        )
+        .subcommand(App::new("status").about("Show which save is currently loaded"))
+        .subcommand(
+            App::new("tui").about("Browse, save, load, remove and restore saves in an interactive interface"),
+        )
+        .subcommand(
+            App::new("bench").about("Measure snapshot and restore performance on the current save"),
+        )
+        .subcommand(
+            App::new("shell-integration")
+                .about("Manage right-click entries in Windows Explorer")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("install").about("Add nauttaja to the Windows Explorer context menu"),
+                )
+                .subcommand(
+                    App::new("uninstall")
+                        .about("Remove nauttaja from the Windows Explorer context menu"),
+                )
+                .subcommand(
+                    App::new("import")
+                        .about("Import a folder chosen in Windows Explorer")
+                        .setting(AppSettings::Hidden)
+                        .arg(
+                            Arg::new("path")
+                                .about("Path to the directory to import")
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("config")
+                .about("Copy settings between machines")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("export")
+                        .about("Write the settings to a file, leaving out the paths of this machine")
+                        .arg(
+                            Arg::new("file")
+                                .about("File to write the settings to")
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    App::new("import")
+                        .about("Replace the settings with those in a file exported by another machine")
+                        .arg(
+                            Arg::new("file")
+                                .about("File to read the settings from")
+                                .required(true),
+                        ),
+                ),
+        )
         .subcommand(
             App::new("set-noita-dir")
                 .about("Set path to Noitas root directory")
@@ -116,7 +827,112 @@ fn main() {
                 ),
         );
 
-    let matches = app.clone().get_matches();
+    // Decided before anything is written
+    output::set_json(env::args().any(|arg| arg == "--json"));
+
+    // The sandbox must be in place before the config is read, though it is
+    // only reported once it is known whether to be quiet
+    let sandbox = if env::args().any(|arg| arg == "--sandbox") {
+        match sandbox::enter() {
+            Ok(sandbox) => Some(sandbox),
+            Err(error) => {
+                say!("Failed to create sandbox: {}", error);
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    // Likewise for another data directory, which may have to be left untouched
+    let raw_args: Vec<String> = env::args().collect();
+    let data_dir =
+        raw_args
+            .iter()
+            .enumerate()
+            .find_map(|(index, arg)| match arg.strip_prefix("--data-dir") {
+                Some("") => raw_args.get(index + 1).cloned(),
+                Some(value) => value.strip_prefix('=').map(str::to_string),
+                None => None,
+            });
+    if let Some(data_dir) = data_dir {
+        if !Path::new(&data_dir).is_dir() {
+            say!("Failed to find the data directory [{}]", data_dir);
+            return;
+        }
+        env::set_var("NAUTTAJA_STORAGE_ROOT", &data_dir);
+    }
+    READ_ONLY.store(
+        raw_args.iter().any(|arg| arg == "--read-only"),
+        Ordering::Relaxed,
+    );
+
+    let args = apply_command_defaults(&app, env::args().collect());
+    let matches = app.clone().get_matches_from(args);
+
+    progress::set_mode(if matches.is_present("quiet") {
+        Some(progress::Mode::Off)
+    } else {
+        matches.value_of("progress").and_then(progress::parse_mode)
+    });
+    ASSUME_YES.store(matches.is_present("yes"), Ordering::Relaxed);
+    output::init_logging(
+        matches.occurrences_of("verbose"),
+        matches.is_present("quiet"),
+    );
+    output::set_color(!matches.is_present("no-color"));
+    if let Some(sandbox) = sandbox {
+        say!("Running in sandbox [{}]", sandbox.display());
+    }
+    DRY_RUN.store(matches.is_present("dry-run"), Ordering::Relaxed);
+    fault::inject(
+        matches
+            .values_of("inject-fault")
+            .into_iter()
+            .flatten()
+            .map(|fault| fault::parse(fault).expect("Validated by clap"))
+            .collect(),
+    );
+
+    if read_only() {
+        let inspecting = match matches.subcommand() {
+            Some(("trash", matches)) => !matches!(
+                matches.subcommand_name(),
+                Some("restore" | "delete" | "empty")
+            ),
+            Some((name, _)) => READ_ONLY_COMMANDS.contains(&name),
+            None => true,
+        };
+        if !inspecting {
+            say!(
+                "Only {} and trash list are available with --read-only",
+                READ_ONLY_COMMANDS.join(", ")
+            );
+            return;
+        }
+        if matches.is_present("repair") {
+            say!("The gamedb cannot be repaired with --read-only");
+            return;
+        }
+    }
+
+    if dry_run() {
+        let supported = match matches.subcommand_name() {
+            Some(name) => DRY_RUN_COMMANDS.contains(&name) || READ_ONLY_COMMANDS.contains(&name),
+            None => true,
+        };
+        if !supported {
+            say!(
+                "--dry-run is only available with {} and the commands which inspect saves",
+                DRY_RUN_COMMANDS.join(", ")
+            );
+            return;
+        }
+        if matches.is_present("repair") {
+            say!("The gamedb cannot be repaired with --dry-run");
+            return;
+        }
+    }
 
     if let Some(matches) = matches.subcommand_matches("set-noita-dir") {
         let path = matches.value_of("path").unwrap(); // Required argument
@@ -124,18 +940,71 @@ fn main() {
         return;
     }
 
-    let gamedb = load_gamedb();
-    if gamedb.is_err() {
-        println!(
-            "Could not load gamedb. Run nauttaja set-noita-dir <path to Noitas root directory>"
-        );
+    let config = match config::load() {
+        Ok(config) => config,
+        // Inspecting saves does not involve Noita, so no config is needed
+        Err(_) if read_only() => Config::default(),
+        Err(CliError::Config(message)) => {
+            say!("{}", message);
+            return;
+        }
+        Err(_) => {
+            say!(
+                "Could not load config. Run nauttaja set-noita-dir <path to Noitas root directory>"
+            );
+            return;
+        }
+    };
+
+    // Runs before the gamedb is checked, since a malformed gamedb is what it fixes
+    if matches.subcommand_matches("recover").is_some() {
+        recover::run().expect("Failed to recover");
         return;
     }
-    let gamedb = gamedb.unwrap();
+
+    let needs_gamedb = match matches.subcommand() {
+        Some(("shell-integration", matches)) => matches.subcommand_name() == Some("import"),
+        Some((name, _)) => !GAMEDB_FREE_COMMANDS.contains(&name),
+        None => false,
+    };
+    let repair = matches.is_present("repair");
+    if repair {
+        gamedb::repair().expect("Failed to repair gamedb");
+    } else if needs_gamedb {
+        match load_gamedb() {
+            Ok(gamedb) => {
+                if let Some(version) = gamedb.version.filter(|version| is_newer(version)) {
+                    say!(
+                        "Warning: the gamedb was last written by nauttaja {}, which is newer than this version ({})",
+                        version,
+                        crate_version!()
+                    );
+                    say!("What only the newer version knows about is kept, but it is not used");
+                }
+            }
+            Err(CliError::GameDB(message)) => {
+                say!("{}", message);
+                return;
+            }
+            Err(_) => {}
+        }
+    }
+
+    if needs_gamedb && !read_only() && !dry_run() {
+        if let Err(error) = expire_trash(&config) {
+            say!("Failed to delete expired saves from the trash: {}", error);
+        }
+        if let Err(error) = enforce_trash_quota(&config) {
+            say!("Failed to keep the trash within its quota: {}", error);
+        }
+        if let Err(error) = pending_delete::purge_expired(&config) {
+            say!("Failed to purge deleted saves: {}", error);
+        }
+    }
 
     if let Some(matches) = matches.subcommand_matches("open") {
         if matches.is_present("noita") {
-            open_explorer_in(&gamedb.config.noita_root_dir);
+            open_explorer_in(&config.noita_root_dir);
         } else {
             open_explorer_in(
                 nauttaja_dir()
@@ -145,68 +1014,648 @@ fn main() {
             );
         }
     } else if let Some(matches) = matches.subcommand_matches("save") {
-        let name = matches.value_of("name").unwrap(); // Required argument
-        save_game(&gamedb.config, name).expect("Failed to save game");
+        let gamedb = load_gamedb().expect("Failed to load saves");
+        let name = match matches.value_of("name") {
+            Some(name) => name.to_string(),
+            None => generate_name(&config, &gamedb, &noita_save_dir(&config), "")
+                .expect("Failed to generate name"),
+        };
+        save_game(
+            &config,
+            &gamedb,
+            &name,
+            matches.value_of("note"),
+            matches.is_present("force"),
+            matches.is_present("overwrite"),
+            matches.is_present("verify"),
+        )
+        .expect("Failed to save game");
     } else if let Some(matches) = matches.subcommand_matches("load") {
         if let Some(name) = matches.value_of("name") {
-            load_save(&gamedb.config, name).expect("Failed to load save");
+            load_save(
+                &config,
+                name,
+                !matches.is_present("no-backup"),
+                matches.is_present("verify"),
+                matches.is_present("cross-profile"),
+            )
+            .expect("Failed to load save");
+        } else if can_pick() {
+            let gamedb = load_gamedb().expect("Failed to load saves");
+            if let Some(name) = pick_save(&gamedb.saves, "load").expect("Failed to select a save") {
+                load_save(
+                    &config,
+                    &name,
+                    !matches.is_present("no-backup"),
+                    matches.is_present("verify"),
+                    matches.is_present("cross-profile"),
+                )
+                .expect("Failed to load save");
+            }
         } else {
-            println!("Please specify which save to load");
-            list_saves().expect("Failed to list saves");
+            say!("Please specify which save to load");
+            list_saves(&config, &ListOptions::default()).expect("Failed to list saves");
         }
+    } else if let Some(matches) = matches.subcommand_matches("autosave") {
+        let every = parse_interval(matches.value_of("every").unwrap()) // Default value
+            .expect("Validated by clap");
+        let keep = matches
+            .value_of("keep")
+            .unwrap() // Default value
+            .parse()
+            .expect("Validated by clap");
+        autosave(&config, every, keep).expect("Failed to autosave");
+    } else if matches.subcommand_matches("watch").is_some() {
+        watch(&config).expect("Failed to watch Noita");
+    } else if let Some(matches) = matches.subcommand_matches("checkpoint") {
+        let label = matches.value_of("label").unwrap(); // Required argument
+        checkpoint(label).expect("Failed to keep the latest autosave");
+    } else if let Some(matches) = matches.subcommand_matches("qs") {
+        let slot = matches
+            .value_of("slot")
+            .map_or(1, |slot| slot.parse().expect("Validated by clap"));
+        quicksave(&config, slot).expect("Failed to quicksave");
+    } else if let Some(matches) = matches.subcommand_matches("ql") {
+        let slot = matches
+            .value_of("slot")
+            .map_or(1, |slot| slot.parse().expect("Validated by clap"));
+        quickload(
+            &config,
+            slot,
+            !matches.is_present("no-backup"),
+            matches.is_present("cross-profile"),
+        )
+        .expect("Failed to quickload");
     } else if let Some(matches) = matches.subcommand_matches("list") {
-        if matches.is_present("removed") {
-            list_trash()
+        let mut filter: Vec<&str> = matches.values_of("filter").into_iter().flatten().collect();
+        let removed = filter.first() == Some(&"removed");
+        if removed {
+            filter.remove(0);
+        }
+        let pattern = match filter.first() {
+            Some(pattern) => match list_pattern(pattern) {
+                Ok(pattern) => Some(pattern),
+                Err(error) => {
+                    say!("Invalid pattern: {}", error);
+                    return;
+                }
+            },
+            None => None,
+        };
+        let options = ListOptions {
+            pattern,
+            size: matches.is_present("size"),
+            details: matches.is_present("details"),
+            min_orbs: matches
+                .value_of("min-orbs")
+                .map(|orbs| orbs.parse().expect("Validated by clap")),
+            sort: match matches.value_of("sort") {
+                Some("name") => SortOrder::Name,
+                Some("size") => SortOrder::Size,
+                Some("last-loaded") => SortOrder::LastLoaded,
+                _ => SortOrder::Date,
+            },
+            reverse: matches.is_present("reverse"),
+        };
+        if removed {
+            list_trash(&config, &options)
         } else {
-            list_saves()
+            list_saves(&config, &options)
         }
         .expect("Failed to list saves");
     } else if let Some(matches) = matches.subcommand_matches("remove") {
-        if let Some(name) = matches.value_of("name") {
-            remove_save(name).expect("Failed to remove save");
+        if matches.is_present("interactive") {
+            let gamedb = load_gamedb().expect("Failed to load saves");
+            let names = select_saves(&gamedb.saves, "remove").expect("Failed to select saves");
+            if !names.is_empty() {
+                remove_saves(
+                    &gamedb,
+                    &names.iter().map(String::as_str).collect::<Vec<_>>(),
+                )
+                .expect("Failed to remove saves");
+            }
+        } else if let Some(names) = matches.values_of("name") {
+            let gamedb = load_gamedb().expect("Failed to load saves");
+            let names = expand_wildcards(names.collect(), &gamedb.saves);
+            remove_saves(&gamedb, &names).expect("Failed to remove saves");
+        } else if can_pick() {
+            let gamedb = load_gamedb().expect("Failed to load saves");
+            if let Some(name) = pick_save(&gamedb.saves, "remove").expect("Failed to select a save")
+            {
+                remove_saves(&gamedb, &[&name]).expect("Failed to remove saves");
+            }
         } else {
-            println!("Please specify which save to remove");
-            list_trash().expect("Failed to list saves");
+            say!("Please specify which save to remove");
+            list_saves(&config, &ListOptions::default()).expect("Failed to list saves");
         }
-    } else if let Some(matches) = matches.subcommand_matches("restore") {
-        if let Some(name) = matches.value_of("name") {
-            restore_save(name).expect("Failed to restore save");
+    } else if let Some(matches) = matches.subcommand_matches("prune") {
+        let keep_last = matches
+            .value_of("keep-last")
+            .map_or(config.prune_keep_last, |keep| {
+                keep.parse().expect("Validated by clap")
+            });
+        let keep_days = matches
+            .value_of("keep-days")
+            .map_or(config.prune_keep_days, |days| {
+                days.parse().expect("Validated by clap")
+            });
+        prune(keep_last, keep_days).expect("Failed to prune saves");
+    } else if let Some(matches) = matches.subcommand_matches("rename") {
+        let old = matches.value_of("old").unwrap(); // Required argument
+        let new = matches.value_of("new").unwrap(); // Required argument
+        rename_save(old, new).expect("Failed to rename save");
+    } else if let Some(matches) = matches.subcommand_matches("rollback") {
+        let steps = matches
+            .value_of("steps")
+            .map_or(1, |steps| steps.parse().expect("Validated by clap"));
+        rollback(
+            &config,
+            steps,
+            !matches.is_present("no-backup"),
+            matches.is_present("cross-profile"),
+        )
+        .expect("Failed to roll back");
+    } else if let Some(matches) = matches.subcommand_matches("clone") {
+        let name = matches.value_of("name").unwrap(); // Required argument
+        let new = matches.value_of("new").unwrap(); // Required argument
+        clone_save(&config, name, new).expect("Failed to clone save");
+    } else if let Some(matches) = matches.subcommand_matches("note") {
+        let name = matches.value_of("name").unwrap(); // Required argument
+        let note = if matches.is_present("clear") {
+            Some(None)
         } else {
-            println!("Please specify which save to restore");
-            list_trash().expect("Failed to list saves");
-        }
+            matches.value_of("text").map(Some)
+        };
+        note_save(name, note).expect("Failed to update note");
+    } else if let Some(matches) = matches.subcommand_matches("restore") {
+        run_restore(&config, matches);
     } else if let Some(matches) = matches.subcommand_matches("delete") {
-        if let Some(name) = matches.value_of("name") {
-            delete_save(name).expect("Failed to delete save");
+        run_delete(&config, matches);
+    } else if matches.subcommand_matches("empty-trash").is_some() {
+        empty_trash(&config).expect("Failed to empty the trash");
+    } else if let Some(matches) = matches.subcommand_matches("trash") {
+        if let Some(matches) = matches.subcommand_matches("restore") {
+            run_restore(&config, matches);
+        } else if let Some(matches) = matches.subcommand_matches("delete") {
+            run_delete(&config, matches);
+        } else if matches.subcommand_matches("empty").is_some() {
+            empty_trash(&config).expect("Failed to empty the trash");
         } else {
-            println!("Please specify which save to permanently delete");
-            println!("Note that you can only permanently delete removed saves");
-            list_trash().expect("Failed to list saves");
+            list_trash(&config, &ListOptions::default()).expect("Failed to list saves");
         }
     } else if let Some(matches) = matches.subcommand_matches("import") {
+        if let Some(mut values) = matches.values_of("from-nauttaja") {
+            let source = values.next().unwrap(); // At least one value
+            let names: Vec<&str> = values.collect();
+            import_from_nauttaja(&config, Path::new(source), &names, repair)
+                .expect("Failed to import saves");
+            return;
+        }
         let path = matches.value_of("path").unwrap(); // Required argument
         let name = matches.value_of("name").unwrap(); // Required argument
-        import_save(path, name).expect("Failed to import save");
+        import_save(&config, path, name, matches.value_of("note"), repair)
+            .expect("Failed to import save");
+    } else if let Some(matches) = matches.subcommand_matches("verify") {
+        let summary = matches.is_present("summary");
+        if matches.is_present("all") {
+            verify_saves(None, summary).expect("Failed to verify saves");
+        } else if let Some(name) = matches.value_of("name") {
+            verify_saves(Some(name), summary).expect("Failed to verify save");
+        } else {
+            say!("Please specify which save to verify");
+            list_saves(&config, &ListOptions::default()).expect("Failed to list saves");
+        }
+    } else if matches.subcommand_matches("scrub").is_some() {
+        verify_saves(None, false).expect("Failed to verify saves");
+    } else if let Some(matches) = matches.subcommand_matches("shell-integration") {
+        if matches.subcommand_matches("install").is_some() {
+            shell_integration::install().expect("Failed to install shell integration");
+        } else if matches.subcommand_matches("uninstall").is_some() {
+            shell_integration::uninstall().expect("Failed to uninstall shell integration");
+        } else if let Some(matches) = matches.subcommand_matches("import") {
+            let path = matches.value_of("path").unwrap(); // Required argument
+            shell_integration::import(&config, path).expect("Failed to import save");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("compress") {
+        let names: Vec<&str> = matches.values_of("names").into_iter().flatten().collect();
+        compress_saves(&config, &names).expect("Failed to compress saves");
+    } else if let Some(matches) = matches.subcommand_matches("config") {
+        if let Some(matches) = matches.subcommand_matches("export") {
+            let file = matches.value_of("file").unwrap(); // Required argument
+            export_config(&config, file).expect("Failed to export settings");
+        } else if let Some(matches) = matches.subcommand_matches("import") {
+            let file = matches.value_of("file").unwrap(); // Required argument
+            import_config(&config, file).expect("Failed to import settings");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("maintain") {
+        let scrub = matches
+            .value_of("scrub")
+            .map(|count| count.parse().expect("Validated by clap"));
+        maintain(&config, scrub).expect("Failed to maintain saves");
+    } else if matches.subcommand_matches("gc").is_some() {
+        pending_delete::purge_all().expect("Failed to purge deleted saves");
+        let (removed, freed) = collect_store_garbage().expect("Failed to clean up the store");
+        if removed > 0 {
+            say!(
+                "Removed {} files no save has from the store, {} freed",
+                removed,
+                human_size(freed)
+            );
+        }
+    } else if let Some(matches) = matches.subcommand_matches("info") {
+        let name = matches.value_of("name").unwrap(); // Required argument
+        save_info(name, matches.is_present("inventory")).expect("Failed to show save");
+    } else if let Some(matches) = matches.subcommand_matches("diff") {
+        let a = matches.value_of("a").unwrap(); // Required argument
+        let b = matches.value_of("b").unwrap(); // Required argument
+        diff_saves(a, b, matches.is_present("stats")).expect("Failed to compare saves");
+    } else if let Some(matches) = matches.subcommand_matches("journal") {
+        match matches.value_of("run") {
+            Some(run) => run_journal(run),
+            None => session_journal(),
+        }
+        .expect("Failed to write journal");
+    } else if let Some(matches) = matches.subcommand_matches("search") {
+        let items: Vec<&str> = matches.values_of("has").unwrap().collect(); // Required argument
+        search_saves(&items).expect("Failed to search saves");
+    } else if let Some(matches) = matches.subcommand_matches("which") {
+        let query = matches.value_of("query").unwrap(); // Required argument
+        which(query).expect("Failed to resolve the name");
+    } else if let Some(matches) = matches.subcommand_matches("index") {
+        let mut gamedb = load_gamedb().expect("Failed to load gamedb");
+        let indexed =
+            index_saves(&mut gamedb, matches.is_present("rebuild")).expect("Failed to index saves");
+        say!("Indexed {} saves", indexed);
+    } else if matches.subcommand_matches("status").is_some() {
+        status(&config).expect("Failed to show status");
+    } else if matches.subcommand_matches("tui").is_some() {
+        if tui::available() {
+            tui::run(&config).expect("Failed to run the interface");
+        } else {
+            say!("nauttaja tui needs a terminal to run in");
+        }
+    } else if matches.subcommand_matches("bench").is_some() {
+        bench(&config).expect("Failed to run benchmark");
     } else {
         app.print_help().unwrap();
     }
 }
 
+fn run_restore(config: &Config, matches: &ArgMatches) {
+    let gamedb = load_gamedb().expect("Failed to load saves");
+    if matches.is_present("all") {
+        restore_all(&gamedb).expect("Failed to restore saves");
+    } else if matches.is_present("interactive") {
+        let names = select_saves(&gamedb.trash, "restore").expect("Failed to select saves");
+        if !names.is_empty() {
+            restore_saves(
+                &gamedb,
+                &names.iter().map(String::as_str).collect::<Vec<_>>(),
+            )
+            .expect("Failed to restore saves");
+        }
+    } else if let Some(names) = matches.values_of("name") {
+        let names = expand_wildcards(names.collect(), &gamedb.trash);
+        restore_saves(&gamedb, &names).expect("Failed to restore saves");
+    } else if can_pick() {
+        if let Some(name) = pick_save(&gamedb.trash, "restore").expect("Failed to select a save") {
+            restore_saves(&gamedb, &[&name]).expect("Failed to restore saves");
+        }
+    } else {
+        say!("Please specify which save to restore");
+        list_trash(config, &ListOptions::default()).expect("Failed to list saves");
+    }
+}
+
+fn run_delete(config: &Config, matches: &ArgMatches) {
+    let gamedb = load_gamedb().expect("Failed to load saves");
+    if matches.is_present("interactive") {
+        let names =
+            select_saves(&gamedb.trash, "permanently delete").expect("Failed to select saves");
+        if !names.is_empty() {
+            delete_saves(
+                config,
+                &gamedb,
+                &names.iter().map(String::as_str).collect::<Vec<_>>(),
+            )
+            .expect("Failed to delete saves");
+        }
+    } else if let Some(names) = matches.values_of("name") {
+        let names = expand_wildcards(names.collect(), &gamedb.trash);
+        delete_saves(config, &gamedb, &names).expect("Failed to delete saves");
+    } else {
+        say!("Please specify which save to permanently delete");
+        say!("Note that you can only permanently delete removed saves");
+        list_trash(config, &ListOptions::default()).expect("Failed to list saves");
+    }
+}
+
+// Characters which make a list pattern a regular expression rather than wildcards
+const REGEX_SYNTAX: &[char] = &['.', '^', '$', '+', '(', ')', '[', ']', '{', '}', '|', '\\'];
+
+// Plain words match anywhere in a name, wildcards whole names
+fn list_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    wildcard(pattern)
+        .filter(|_| !pattern.contains(REGEX_SYNTAX))
+        .map_or_else(
+            || RegexBuilder::new(pattern).case_insensitive(true).build(),
+            Ok,
+        )
+}
+
+// Matches whole names, where * stands for anything and ? for any one character
+fn wildcard(pattern: &str) -> Option<Regex> {
+    if !pattern.contains(['*', '?']) {
+        return None;
+    }
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    RegexBuilder::new(&regex)
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+// Replaces names with wildcards by the names of the saves they match, newest
+// first. Exact names take precedence, and patterns matching nothing are kept
+// so they are reported as missing.
+fn expand_wildcards<'a>(names: Vec<&'a str>, saves: &'a [Save]) -> Vec<&'a str> {
+    let mut saves: Vec<&Save> = saves.iter().collect();
+    saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    let mut expanded = Vec::new();
+    for name in names {
+        let pattern = wildcard(name).filter(|_| !saves.iter().any(|save| save.name == name));
+        let matched: Vec<&str> = match pattern {
+            Some(pattern) => saves
+                .iter()
+                .filter(|save| pattern.is_match(&save.name))
+                .map(|save| save.name.as_str())
+                .collect(),
+            None => Vec::new(),
+        };
+        let matched = if matched.is_empty() {
+            vec![name]
+        } else {
+            matched
+        };
+        for name in matched {
+            if !expanded.contains(&name) {
+                expanded.push(name);
+            }
+        }
+    }
+    expanded
+}
+
+// Picks any number of saves, ticking them off with the arrow keys and Space on
+// terminals. Otherwise the saves are listed with a number each and picked by
+// numbers and ranges such as "1 3 5-8". Nothing is picked on an empty answer.
+fn select_saves(saves: &[Save], action: &str) -> Result<Vec<String>, CliError> {
+    let mut saves = saves.to_vec();
+    ListOptions::default().sort(&mut saves);
+    if saves.is_empty() {
+        say!("No saves to {}", action);
+        return Ok(Vec::new());
+    }
+    if can_pick() {
+        let items: Vec<String> = saves
+            .iter()
+            .map(|save| format!("{} - {}", save.timestamp, save.name))
+            .collect();
+        let picked = dialoguer::MultiSelect::new()
+            .with_prompt(format!(
+                "Saves to {}, Space to pick, Enter to accept or Esc to cancel",
+                action
+            ))
+            .items(&items)
+            .interact_opt()
+            .map_err(|dialoguer::Error::IO(error)| error)?;
+        return match picked {
+            Some(picked) if !picked.is_empty() => Ok(picked
+                .into_iter()
+                .map(|index| saves[index].name.clone())
+                .collect()),
+            _ => {
+                say!("Aborted, nothing was picked");
+                Ok(Vec::new())
+            }
+        };
+    }
+    // Part of the question, so the saves are shown wherever it is asked
+    let mut question: String = saves
+        .iter()
+        .enumerate()
+        .map(|(number, save)| format!("{:>4}  {} - {}\n", number + 1, save.timestamp, save.name))
+        .collect();
+    question.push_str(&format!(
+        "Saves to {}, such as 1 3 5-8, or nothing to cancel:",
+        action
+    ));
+    loop {
+        let answer = prompt(&question)?;
+        if answer.is_empty() {
+            say!("Aborted, nothing was picked");
+            return Ok(Vec::new());
+        }
+        match parse_selection(&answer, saves.len()) {
+            Ok(picked) => {
+                return Ok(picked
+                    .into_iter()
+                    .map(|number| saves[number - 1].name.clone())
+                    .collect())
+            }
+            Err(message) => say!("{}", message),
+        }
+    }
+}
+
+// Whether a save can be picked with pick_save, rather than listing the saves
+// and asking for a name
+fn can_pick() -> bool {
+    !output::is_json() && io::stdin().is_terminal() && io::stderr().is_terminal()
+}
+
+// Picks a single save using the arrow keys, typing narrows down the saves
+fn pick_save(saves: &[Save], action: &str) -> Result<Option<String>, CliError> {
+    let mut saves = saves.to_vec();
+    ListOptions::default().sort(&mut saves);
+    if saves.is_empty() {
+        say!("No saves to {}", action);
+        return Ok(None);
+    }
+    let items: Vec<String> = saves
+        .iter()
+        .map(|save| format!("{} - {}", save.timestamp, save.name))
+        .collect();
+    let picked = dialoguer::FuzzySelect::new()
+        .with_prompt(format!(
+            "Save to {}, type to filter or Esc to cancel",
+            action
+        ))
+        .items(&items)
+        .default(0)
+        .interact_opt()
+        .map_err(|dialoguer::Error::IO(error)| error)?;
+    match picked {
+        Some(index) => Ok(Some(saves[index].name.clone())),
+        None => {
+            say!("Aborted, nothing was picked");
+            Ok(None)
+        }
+    }
+}
+
+fn parse_selection(answer: &str, count: usize) -> Result<Vec<usize>, String> {
+    let mut picked = Vec::new();
+    let number = |value: &str| match value.trim().parse::<usize>() {
+        Ok(number) if (1..=count).contains(&number) => Ok(number),
+        _ => Err(format!(
+            "Expected numbers from 1 to {}, not {}",
+            count, value
+        )),
+    };
+    for part in answer.split(|c: char| c == ',' || c.is_whitespace()) {
+        if part.is_empty() {
+            continue;
+        }
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (number(first)?, number(last)?),
+            None => (number(part)?, number(part)?),
+        };
+        for number in first.min(last)..=first.max(last) {
+            if !picked.contains(&number) {
+                picked.push(number);
+            }
+        }
+    }
+    picked.sort_unstable();
+    Ok(picked)
+}
+
+fn apply_command_defaults(app: &App, mut args: Vec<String>) -> Vec<String> {
+    let config = match config::load() {
+        Ok(config) => config,
+        Err(_) => return args,
+    };
+    let index = match subcommand_index(app, &args) {
+        Some(index) => index,
+        None => return args,
+    };
+    let subcommand = match app.find_subcommand(args[index].as_str()) {
+        Some(subcommand) => subcommand,
+        None => return args,
+    };
+    let defaults = match config.defaults.get(subcommand.get_name()) {
+        Some(defaults) => defaults,
+        None => return args,
+    };
+
+    let mut injected = Vec::new();
+    for (flag, value) in defaults {
+        if !subcommand
+            .get_arguments()
+            .any(|arg| arg.get_long() == Some(flag.as_str()))
+        {
+            say!(
+                "Ignoring default for unknown option [{}.{}]",
+                subcommand.get_name(),
+                flag
+            );
+            continue;
+        }
+        let long = format!("--{}", flag);
+        let explicit = args[index + 1..]
+            .iter()
+            .any(|arg| *arg == long || arg.starts_with(&format!("{}=", long)));
+        if explicit {
+            continue;
+        }
+        match value {
+            Value::Bool(true) => injected.push(long),
+            Value::Bool(false) | Value::Null => {}
+            Value::Array(values) => {
+                for value in values {
+                    injected.push(long.clone());
+                    injected.push(default_value_to_string(value));
+                }
+            }
+            value => {
+                injected.push(long);
+                injected.push(default_value_to_string(value));
+            }
+        }
+    }
+    args.splice(index + 1..index + 1, injected);
+    args
+}
+
+// The subcommand is the first argument which is neither an option nor the
+// value of one, so `--data-dir D load` is not taken for the subcommand D
+fn subcommand_index(app: &App, args: &[String]) -> Option<usize> {
+    let takes_value = |arg: &str| {
+        app.get_arguments().any(|option| {
+            let matches = match arg.strip_prefix("--") {
+                Some(long) => option.get_long() == Some(long),
+                None => arg.len() == 2 && option.get_short() == arg.chars().nth(1),
+            };
+            matches && option.is_set(ArgSettings::TakesValue)
+        })
+    };
+    let mut index = 1;
+    while index < args.len() {
+        let arg = &args[index];
+        if arg == "--" {
+            return None;
+        }
+        if !arg.starts_with('-') {
+            return Some(index);
+        }
+        index += if takes_value(arg) { 2 } else { 1 };
+    }
+    None
+}
+
+fn default_value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
 fn update_noita_dir(noita_path: &str) {
-    update_gamedb(|mut gamedb: GameDB| {
-        gamedb.config.noita_root_dir = noita_path.to_string();
-        gamedb
-    })
-    .expect("Failed to update Noita directory");
+    config::set_noita_root_dir(noita_path).expect("Failed to update Noita directory");
+}
+
+// Held while the current save is replaced, so autosaves never copy a save which
+// is only partially loaded
+fn lock_current_save() -> Result<fs::File, CliError> {
+    let dir = nauttaja_dir()?;
+    fs::create_dir_all(&dir)?;
+    let lock = fs::File::create(dir.join(NAUTTAJA_CURRENT_SAVE_LOCK_FILE))?;
+    lock.lock()?;
+    Ok(lock)
 }
 
 fn update_gamedb<T>(mut update_fn: T) -> Result<(), CliError>
 where
     T: FnMut(GameDB) -> GameDB,
 {
+    // Whatever is computed along the way, such as sizes, is simply not stored
+    if read_only() || dry_run() {
+        return Ok(());
+    }
     let nauttaja_dir = nauttaja_dir()?;
     let gamedb_file = nauttaja_dir.join(NAUTTAJA_GAMEDB_FILE);
-    fs::create_dir_all(nauttaja_dir)?;
+    // Read, update and write while holding the lock, so concurrent updates
+    // are applied one after another instead of overwriting each other
+    let _lock = gamedb::lock(&nauttaja_dir)?;
     let gamedb = if gamedb_file.exists() {
         load_gamedb()?
     } else {
@@ -215,223 +1664,3663 @@ where
         }
     };
 
-    let gamedb = update_fn(gamedb);
-    fs::write(gamedb_file, serde_json::to_string_pretty(&gamedb)?)?;
+    let mut gamedb = update_fn(gamedb);
+    gamedb.version = Some(crate_version!().to_string());
+    gamedb::write(&gamedb_file, &gamedb)
+}
+
+fn delete_saves(config: &Config, gamedb: &GameDB, save_names: &[&str]) -> Result<(), CliError> {
+    if dry_run() {
+        for save_name in save_names {
+            if let Some(save) = gamedb.trash.iter().find(|item| item.name == *save_name) {
+                say!("Would remove [{}] from the trash", save_name);
+                say!("{}", pending_delete::describe(config, save)?);
+            } else if gamedb.saves.iter().any(|item| item.name == *save_name) {
+                say!("Found save [{}], currently not in the trash", save_name);
+            } else {
+                say!("{}", not_found(save_name, &gamedb.trash));
+            }
+        }
+        return Ok(());
+    }
+    let question = match save_names {
+        [save_name] => format!("Permanently delete [{}]?", save_name),
+        _ => format!(
+            "Permanently delete {} saves ({})?",
+            save_names.len(),
+            save_names.join(", ")
+        ),
+    };
+    if !confirm(&question)? {
+        say!("Aborted, nothing was deleted");
+        return Ok(());
+    }
+
+    let mut to_delete = Vec::new();
+    let mut not_removed = Vec::new();
+    let mut missing = Vec::new();
+    update_gamedb(|mut gamedb: GameDB| {
+        to_delete.clear();
+        not_removed.clear();
+        missing.clear();
+        for save_name in save_names {
+            let index = gamedb.trash.iter().position(|item| item.name == *save_name);
+            if let Some(index) = index {
+                to_delete.push(gamedb.trash.remove(index));
+            } else if gamedb.saves.iter().any(|item| item.name == *save_name) {
+                not_removed.push(*save_name);
+            } else {
+                missing.push(*save_name);
+            }
+        }
+        gamedb
+    })?;
+
+    let mut deleted = 0;
+    for save in &to_delete {
+        match pending_delete::delete(config, save) {
+            Ok(()) => {
+                deleted += 1;
+                say!("Save with name [{}] deleted", save.name);
+            }
+            Err(error) => say!("Failed to delete the files of [{}]: {}", save.name, error),
+        }
+    }
+    for save_name in &not_removed {
+        say!("Found save [{}], currently not in the trash", save_name);
+    }
+    if !not_removed.is_empty() {
+        say!("To permanently delete a save, please trash it first");
+    }
+    for save_name in &missing {
+        say!("{}", not_found(save_name, &gamedb.trash));
+    }
+    if save_names.len() > 1 {
+        say!("Deleted {} of {} saves", deleted, save_names.len());
+    }
+    Ok(())
+}
+
+fn empty_trash(config: &Config) -> Result<(), CliError> {
+    let gamedb = load_gamedb_with_sizes()?;
+    if gamedb.trash.is_empty() {
+        say!("The trash is already empty");
+        return Ok(());
+    }
+
+    let size: u64 = gamedb.trash.iter().filter_map(|save| save.size).sum();
+    say!(
+        "The trash contains {} saves, taking up {}",
+        gamedb.trash.len(),
+        human_size(size)
+    );
+    if dry_run() {
+        for save in &gamedb.trash {
+            say!("Would remove [{}] from the trash", save.name);
+            say!("{}", pending_delete::describe(config, save)?);
+        }
+        return Ok(());
+    }
+    if !confirm("Permanently delete all of them?")? {
+        say!("Aborted, nothing was deleted");
+        return Ok(());
+    }
+
+    let mut deleted = Vec::new();
+    update_gamedb(|mut gamedb: GameDB| {
+        deleted = std::mem::take(&mut gamedb.trash);
+        gamedb
+    })?;
+    for save in &deleted {
+        pending_delete::delete(config, save)?;
+        say!("Deleted [{}]", save.name);
+    }
+
+    say!(
+        "Emptied the trash, {} saves deleted and {} freed",
+        deleted.len(),
+        human_size(size)
+    );
+    Ok(())
+}
+
+// Saves removed before their removal was dated expire counting from now
+fn expire_trash(config: &Config) -> Result<usize, CliError> {
+    let days = match config.trash_retention_days {
+        Some(days) => days,
+        None => return Ok(0),
+    };
+    let cutoff = Local::now().naive_local() - chrono::Duration::days(days.into());
+    let gamedb = load_gamedb()?;
+    let expired = |save: &Save| {
+        save.removed
+            .as_deref()
+            .and_then(parse_timestamp)
+            .is_some_and(|removed| removed <= cutoff)
+    };
+    let undated = |save: &Save| save.removed.as_deref().and_then(parse_timestamp).is_none();
+    if !gamedb
+        .trash
+        .iter()
+        .any(|save| expired(save) || undated(save))
+    {
+        return Ok(0);
+    }
+
+    let mut deleted = Vec::new();
+    update_gamedb(|mut gamedb: GameDB| {
+        let (expired, kept) = gamedb.trash.into_iter().partition(expired);
+        deleted = expired;
+        gamedb.trash = kept;
+        for save in gamedb.trash.iter_mut().filter(|save| undated(save)) {
+            save.removed = Some(timestamp());
+        }
+        gamedb
+    })?;
+    for save in &deleted {
+        pending_delete::delete(config, save)?;
+        say!(
+            "Deleted [{}], which was in the trash for more than {} days",
+            save.name,
+            days
+        );
+    }
+    Ok(deleted.len())
+}
+
+// Saves removed the longest ago go first, sparing the one the current game was
+// loaded from
+fn enforce_trash_quota(config: &Config) -> Result<usize, CliError> {
+    let quota = match config.trash_quota_mib {
+        Some(quota) => quota * 1024 * 1024,
+        None => return Ok(0),
+    };
+    let gamedb = load_gamedb_with_sizes()?;
+    let mut size: u64 = gamedb.trash.iter().filter_map(|save| save.size).sum();
+    if size <= quota {
+        return Ok(0);
+    }
+    let loaded = gamedb
+        .loaded
+        .as_ref()
+        .map(|loaded| loaded.directory.as_str());
+    let mut candidates: Vec<&Save> = gamedb
+        .trash
+        .iter()
+        .filter(|save| Some(save.directory.as_str()) != loaded)
+        .collect();
+    candidates.sort_by(|a, b| a.removed.cmp(&b.removed));
+    let mut over = Vec::new();
+    for save in candidates {
+        if size <= quota {
+            break;
+        }
+        size -= save.size.unwrap_or(0);
+        over.push(save.directory.clone());
+    }
+
+    let mut deleted = Vec::new();
+    update_gamedb(|mut gamedb: GameDB| {
+        let (over, kept) = gamedb
+            .trash
+            .into_iter()
+            .partition(|save| over.contains(&save.directory));
+        deleted = over;
+        gamedb.trash = kept;
+        gamedb
+    })?;
+    for save in &deleted {
+        pending_delete::delete(config, save)?;
+        say!(
+            "Deleted [{}], which was removed the longest ago, to keep the trash within {}",
+            save.name,
+            human_size(quota)
+        );
+    }
+    Ok(deleted.len())
+}
+
+fn rename_save(old_name: &str, new_name: &str) -> Result<(), CliError> {
+    let mut outcome = None;
+    update_gamedb(|mut gamedb: GameDB| {
+        let taken = gamedb
+            .saves
+            .iter()
+            .chain(gamedb.trash.iter())
+            .find(|item| item.name == new_name)
+            .map(|item| item.removed.is_some());
+        let save = gamedb
+            .saves
+            .iter_mut()
+            .chain(gamedb.trash.iter_mut())
+            .find(|item| item.name == old_name);
+        outcome = match (save, taken) {
+            (None, _) => Some(Err(not_found(
+                old_name,
+                gamedb.saves.iter().chain(gamedb.trash.iter()),
+            ))),
+            (Some(_), Some(false)) => Some(Err(format!("[{}] already exists", new_name))),
+            (Some(_), Some(true)) => Some(Err(format!(
+                "[{}] already exists, currently in the trash",
+                new_name
+            ))),
+            (Some(save), None) => {
+                save.name = new_name.to_string();
+                Some(Ok(()))
+            }
+        };
+        gamedb
+    })?;
+
+    match outcome.expect("The gamedb was updated") {
+        Ok(()) => say!("Renamed [{}] to [{}]", old_name, new_name),
+        Err(message) => say!("{}", message),
+    }
+    Ok(())
+}
+
+fn clone_save(config: &Config, save_name: &str, new_name: &str) -> Result<(), CliError> {
+    let gamedb = load_gamedb()?;
+    let save = match gamedb.saves.iter().find(|item| item.name == save_name) {
+        Some(save) => save,
+        None => {
+            say!("{}", not_found(save_name, &gamedb.saves));
+            return Ok(());
+        }
+    };
+    if let Some(taken) = gamedb
+        .saves
+        .iter()
+        .chain(gamedb.trash.iter())
+        .find(|item| item.name == new_name)
+    {
+        match taken.removed {
+            Some(_) => say!("[{}] already exists, currently in the trash", new_name),
+            None => say!("[{}] already exists", new_name),
+        }
+        return Ok(());
+    }
+
+    let clone_dir_name = uuid();
+    let clone_dir = nauttaja_dir()?
+        .join(NAUTTAJA_SAVES_DIRECTORY)
+        .join(&clone_dir_name);
+    // Files hard linked from the original, or from the store, are shared like
+    // those of stored saves, so only the directories are made writable
+    let stored = copy_save_files(config, &save_path(save)?, &clone_dir)? || save.stored;
+    set_save_readonly(&clone_dir, stored, config.immutable_saves)?;
+
+    update_gamedb(|mut gamedb: GameDB| {
+        gamedb.saves.push(Save {
+            name: new_name.to_string(),
+            directory: clone_dir_name.clone(),
+            timestamp: timestamp(),
+            removed: None,
+            stored,
+            // The clone is a save of its own, which is neither rotated nor
+            // kept with the original, and has not been verified or loaded
+            slot: None,
+            autosave: false,
+            health: None,
+            verified: None,
+            loads: None,
+            last_loaded: None,
+            ..save.clone()
+        });
+        gamedb
+    })?;
+    say!("Cloned [{}] as [{}]", save_name, new_name);
+    Ok(())
+}
+
+// The manifest is copied along, its paths are relative to the save. Returns
+// whether the copy shares its files with the original
+fn copy_save_files(config: &Config, from: &Path, to: &Path) -> Result<bool, CliError> {
+    fs::create_dir_all(to)?;
+    let mut shared = false;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            shared |= copy_dir(config, entry.path(), to)?.shares_files();
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(shared)
+}
+
+// Shows the note of a save, or replaces it when `note` is given
+fn note_save(save_name: &str, note: Option<Option<&str>>) -> Result<(), CliError> {
+    let mut found = None;
+    update_gamedb(|mut gamedb: GameDB| {
+        found = gamedb
+            .saves
+            .iter_mut()
+            .chain(gamedb.trash.iter_mut())
+            .find(|item| item.name == save_name)
+            .map(|save| {
+                if let Some(note) = note {
+                    save.note = note.map(str::to_string);
+                }
+                save.note.clone()
+            });
+        gamedb
+    })?;
+
+    match (found, note) {
+        (None, _) => {
+            let gamedb = load_gamedb()?;
+            show!(
+                "{}",
+                not_found(save_name, gamedb.saves.iter().chain(gamedb.trash.iter()))
+            );
+        }
+        (Some(_), Some(None)) => say!("Removed the note of [{}]", save_name),
+        (Some(_), Some(Some(_))) => say!("Updated the note of [{}]", save_name),
+        (Some(None), None) => say!("[{}] has no note", save_name),
+        (Some(Some(current)), None) => show!("{}", current),
+    }
     Ok(())
 }
 
-fn delete_save(save_name: &str) -> Result<(), CliError> {
-    println!("Deleting save with name [{}]", save_name);
-
-    let mut dir_to_delete = None;
-    update_gamedb(|mut gamedb: GameDB| {
-        let index = gamedb.trash.iter().position(|item| item.name == save_name);
-        if index.is_none() {
-            let index = gamedb.saves.iter().position(|item| item.name == save_name);
-            match index {
-                Some(_) => {
-                    println!("Found save [{}], currently not in the trash", save_name);
-                    println!("To permanently delete this save, please trash it first");
-                }
-                None => println!("Failed to find [{}]", save_name),
+// Compresses the world of a save, leaving it as it is when that fails, since
+// the save is usable either way
+fn compress_world(save_dir: &Path, save_name: &str) {
+    let world = save_dir
+        .join(NOITA_SAVE_DIRECTORY)
+        .join(NOITA_WORLD_DIRECTORY);
+    let started = Instant::now();
+    match compress::compress(&world) {
+        Ok(()) => info!(
+            "Compressed [{}] in {:.2?}",
+            world.display(),
+            started.elapsed()
+        ),
+        Err(error) => say!(
+            "Failed to compress [{}], keeping it as it is: {}",
+            save_name,
+            error
+        ),
+    }
+}
+
+fn compress_saves(config: &Config, save_names: &[&str]) -> Result<(), CliError> {
+    if config.dedup_saves {
+        say!("Saves are not compressed with dedup_saves enabled, since their files are shared instead");
+        return Ok(());
+    }
+    let gamedb = load_gamedb()?;
+    let all: Vec<&Save> = gamedb.saves.iter().chain(&gamedb.trash).collect();
+    let mut saves = Vec::new();
+    if save_names.is_empty() {
+        saves = all;
+    } else {
+        for save_name in save_names {
+            match all.iter().find(|save| save.name == *save_name) {
+                Some(save) => saves.push(*save),
+                None => say!("{}", not_found(save_name, &gamedb.saves)),
+            }
+        }
+    }
+
+    let (mut before, mut after) = (0, 0);
+    for save in saves {
+        let save_dir = save_path(save)?;
+        if !save_dir.exists() {
+            continue;
+        }
+        // Compressing would take the files out of the store
+        if save.stored {
+            say!(
+                "Skipping [{}], its files are shared through the store",
+                save.name
+            );
+            continue;
+        }
+        let size = dir::get_size(&save_dir)?;
+        copy::set_readonly(&save_dir, false)?;
+        compress_world(&save_dir, &save.name);
+        copy::set_readonly(&save_dir, config.immutable_saves)?;
+        let compressed = dir::get_size(&save_dir)?;
+        if compressed < size {
+            say!(
+                "Compressed [{}] from {} to {}",
+                save.name,
+                human_size(size),
+                human_size(compressed)
+            );
+        }
+        before += size;
+        after += compressed;
+    }
+    say!(
+        "Saves take up {} on disk, {} less than before",
+        human_size(after),
+        human_size(before.saturating_sub(after))
+    );
+    Ok(())
+}
+
+fn remove_saves(gamedb: &GameDB, save_names: &[&str]) -> Result<(), CliError> {
+    if dry_run() {
+        for save_name in save_names {
+            if gamedb.saves.iter().any(|item| item.name == *save_name) {
+                say!("Would move [{}] to the trash", save_name);
+            } else {
+                say!("{}", not_found(save_name, &gamedb.saves));
+            }
+        }
+        return Ok(());
+    }
+    if save_names.len() > 1
+        && !confirm(&format!(
+            "Remove {} saves ({})?",
+            save_names.len(),
+            save_names.join(", ")
+        ))?
+    {
+        say!("Aborted, nothing was removed");
+        return Ok(());
+    }
+
+    let mut removed = Vec::new();
+    let mut missing = Vec::new();
+    update_gamedb(|mut gamedb: GameDB| {
+        removed.clear();
+        missing.clear();
+        for save_name in save_names {
+            let index = gamedb.saves.iter().position(|item| item.name == *save_name);
+            if let Some(index) = index {
+                let mut save = gamedb.saves.remove(index);
+                save.removed = Some(timestamp());
+                gamedb.trash.push(save);
+                removed.push(*save_name);
+            } else {
+                missing.push(*save_name);
+            }
+        }
+        gamedb
+    })?;
+
+    for save_name in &removed {
+        say!("Save with name [{}] removed", save_name);
+    }
+    for save_name in &missing {
+        say!("{}", not_found(save_name, &gamedb.saves));
+    }
+    if save_names.len() > 1 {
+        say!("Removed {} of {} saves", removed.len(), save_names.len());
+    }
+    Ok(())
+}
+
+// Quicksaves are left alone, since their slots are replaced anyway, and so is
+// the save the current game was loaded from
+fn prune(keep_last: usize, keep_days: u32) -> Result<(), CliError> {
+    let gamedb = load_gamedb()?;
+    let cutoff = Local::now().naive_local() - chrono::Duration::days(keep_days.into());
+    let loaded = gamedb
+        .loaded
+        .as_ref()
+        .map(|loaded| loaded.directory.as_str());
+    let mut saves: Vec<&Save> = gamedb
+        .saves
+        .iter()
+        .filter(|save| save.slot.is_none() && Some(save.directory.as_str()) != loaded)
+        .collect();
+    saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    let pruned: Vec<String> = saves
+        .into_iter()
+        .skip(keep_last)
+        .filter(|save| parse_timestamp(&save.timestamp).is_some_and(|created| created < cutoff))
+        .map(|save| save.name.clone())
+        .collect();
+
+    if pruned.is_empty() {
+        say!(
+            "Nothing to prune, every save is among the latest {} or made within {} days",
+            keep_last,
+            keep_days
+        );
+        return Ok(());
+    }
+    if dry_run() {
+        for name in &pruned {
+            say!("Would move [{}] to the trash", name);
+        }
+        say!("{} saves would be moved to the trash", pruned.len());
+        return Ok(());
+    }
+    update_gamedb(|mut gamedb: GameDB| {
+        let (trashed, kept) = gamedb
+            .saves
+            .into_iter()
+            .partition(|save| pruned.contains(&save.name));
+        gamedb.saves = kept;
+        for mut save in trashed {
+            save.removed = Some(timestamp());
+            gamedb.trash.push(save);
+        }
+        gamedb
+    })?;
+    for name in &pruned {
+        say!("Moved [{}] to the trash", name);
+    }
+    say!(
+        "Pruned {} saves, restore them with nauttaja trash restore <name>",
+        pruned.len()
+    );
+    Ok(())
+}
+
+fn restore_saves(gamedb: &GameDB, save_names: &[&str]) -> Result<(), CliError> {
+    if dry_run() {
+        for save_name in save_names {
+            if gamedb.trash.iter().any(|item| item.name == *save_name) {
+                say!("Would move [{}] out of the trash", save_name);
+            } else {
+                say!("{}", not_found(save_name, &gamedb.trash));
+            }
+        }
+        return Ok(());
+    }
+    let mut restored = Vec::new();
+    let mut missing = Vec::new();
+    update_gamedb(|mut gamedb: GameDB| {
+        restored.clear();
+        missing.clear();
+        for save_name in save_names {
+            let index = gamedb.trash.iter().position(|item| item.name == *save_name);
+            if let Some(index) = index {
+                let mut save = gamedb.trash.remove(index);
+                save.removed = None;
+                gamedb.saves.push(save);
+                restored.push(*save_name);
+            } else {
+                missing.push(*save_name);
+            }
+        }
+        gamedb
+    })?;
+
+    for save_name in &restored {
+        say!("Save with name [{}] restored", save_name);
+    }
+    for save_name in &missing {
+        say!("{}", not_found(save_name, &gamedb.trash));
+    }
+    if save_names.len() > 1 {
+        say!("Restored {} of {} saves", restored.len(), save_names.len());
+    }
+    Ok(())
+}
+
+fn restore_all(gamedb: &GameDB) -> Result<(), CliError> {
+    if gamedb.trash.is_empty() {
+        say!("The trash is empty");
+        return Ok(());
+    }
+    let names: Vec<&str> = gamedb.trash.iter().map(|save| save.name.as_str()).collect();
+    restore_saves(gamedb, &names)
+}
+
+fn import_save(
+    config: &Config,
+    directory: &str,
+    save_name: &str,
+    note: Option<&str>,
+    repair: bool,
+) -> Result<(), CliError> {
+    say!(
+        "Importing directory [{}] as a new save, named [{}]",
+        directory,
+        save_name
+    );
+    if let Some(problem) = check_imported_manifest(Path::new(directory))? {
+        if !repair {
+            say!("Refusing to import: {}", problem);
+            say!("Run the command again with --repair to import it with a new manifest");
+            return Ok(());
+        }
+        say!("Ignoring {}", problem);
+    }
+    if !save_dir_as_save(config, &load_gamedb()?, directory, save_name, note, false)? {
+        return Ok(());
+    }
+    say!(
+        "Successfully imported directory as a save with name [{}]",
+        directory
+    );
+    Ok(())
+}
+
+// Copies saves along with everything known about them, renaming those whose
+// names are already taken
+fn import_from_nauttaja(
+    config: &Config,
+    source: &Path,
+    names: &[&str],
+    repair: bool,
+) -> Result<(), CliError> {
+    let source_gamedb_file = source.join(NAUTTAJA_GAMEDB_FILE);
+    if !source_gamedb_file.exists() {
+        say!("Failed to find a gamedb in [{}]", source.display());
+        return Ok(());
+    }
+    if fs::canonicalize(source)? == fs::canonicalize(nauttaja_dir()?)? {
+        say!("[{}] is the local data directory", source.display());
+        return Ok(());
+    }
+    let source_gamedb = gamedb::load(&source_gamedb_file)?;
+    let mut selected = Vec::new();
+    if names.is_empty() {
+        selected.extend(source_gamedb.saves.iter());
+    }
+    for name in names {
+        match source_gamedb
+            .saves
+            .iter()
+            .chain(source_gamedb.trash.iter())
+            .find(|save| save.name == *name)
+        {
+            Some(save) => selected.push(save),
+            None => say!("Failed to find [{}] in [{}]", name, source.display()),
+        }
+    }
+
+    let mut imported = 0;
+    for save in selected {
+        let source_dir = source.join(NAUTTAJA_SAVES_DIRECTORY).join(&save.directory);
+        if !source_dir.exists() {
+            say!("[{}] Save directory is missing, skipping it", save.name);
+            continue;
+        }
+        if let Some(problem) = check_imported_manifest(&source_dir)? {
+            if !repair {
+                say!("[{}] Skipping it: {}", save.name, problem);
+                continue;
+            }
+        }
+        let gamedb = load_gamedb()?;
+        let name = unique_name(&gamedb, &save.name);
+        let directory = uuid();
+        let save_dir = nauttaja_dir()?
+            .join(NAUTTAJA_SAVES_DIRECTORY)
+            .join(&directory);
+        copy_save_files(config, &source_dir, &save_dir)?;
+
+        // Make sure the copy is complete before it is registered
+        let manifest = match manifest::read(&save_dir) {
+            Ok(Some(expected))
+                if manifest::compare(&expected, &manifest::generate(&save_dir)?).is_ok() =>
+            {
+                expected
+            }
+            Ok(Some(_)) if !repair => {
+                fs::remove_dir_all(&save_dir)?;
+                say!(
+                    "[{}] Skipping it: the files do not match its manifest, run the command again with --repair to import it anyway",
+                    save.name
+                );
+                continue;
+            }
+            _ => {
+                let manifest = manifest::generate(&save_dir)?;
+                manifest::write(&save_dir, &manifest)?;
+                manifest
+            }
+        };
+        copy::set_readonly(&save_dir, config.immutable_saves)?;
+
+        update_gamedb(|mut gamedb: GameDB| {
+            gamedb.saves.push(Save {
+                name: name.clone(),
+                directory: directory.clone(),
+                removed: None,
+                size: Some(manifest.files.iter().map(|file| file.size).sum()),
+                files: Some(manifest.files.len() as u64),
+                // Slots and autosaves are only rotated among the local saves
+                slot: None,
+                autosave: false,
+                ..save.clone()
+            });
+            gamedb
+        })?;
+        if name == save.name {
+            say!("Imported [{}]", name);
+        } else {
+            say!("Imported [{}] as [{}], the name was taken", save.name, name);
+        }
+        imported += 1;
+    }
+    say!("Imported {} saves from [{}]", imported, source.display());
+    Ok(())
+}
+
+// Directories exported from nauttaja carry a manifest, which is replaced on import
+fn check_imported_manifest(directory: &Path) -> Result<Option<String>, CliError> {
+    match manifest::read(directory) {
+        Ok(_) => Ok(None),
+        Err(CliError::Serde(error)) => Ok(Some(format!(
+            "invalid manifest [{}] at {}, expected a list of files, each with a path, size and hash",
+            directory.join(manifest::MANIFEST_FILE).display(),
+            describe_json_error(&error, (1, 1))
+        ))),
+        Err(error) => Err(error),
+    }
+}
+
+fn save_game(
+    config: &Config,
+    gamedb: &GameDB,
+    save_name: &str,
+    note: Option<&str>,
+    force: bool,
+    overwrite: bool,
+    verify: bool,
+) -> Result<(), CliError> {
+    say!("Saving game with name [{}]", save_name);
+    let noita_save_dir = noita_save_dir(config);
+    if !noita_save_dir.exists() {
+        say!(
+            "Failed to find the current save at [{}]",
+            noita_save_dir.display()
+        );
+        return Ok(());
+    }
+    if let Some(problem) = check_current_save(&noita_save_dir)? {
+        match config.suspicious_save {
+            SuspiciousSavePolicy::Warn => say!("Warning: {}", problem),
+            SuspiciousSavePolicy::Skip => {
+                say!("Skipping save: {}", problem);
+                return Ok(());
+            }
+        }
+    }
+    if !force {
+        if let Some(identical) = find_identical_save(gamedb, &noita_save_dir)? {
+            say!("The current save is identical to {}", identical);
+            say!("Run the command again with --force to save it anyway");
+            return Ok(());
+        }
+    }
+    if !save_dir_as_save(config, gamedb, &noita_save_dir, save_name, note, overwrite)? {
+        return Ok(());
+    }
+    if let Some(save) = load_gamedb()?
+        .saves
+        .into_iter()
+        .find(|save| save.name == save_name)
+    {
+        let save_dir = save_path(&save)?;
+        if let Some(manifest) = manifest::read(&save_dir)? {
+            let prefix = format!("{}/", NOITA_SAVE_DIRECTORY);
+            if verify {
+                say!("Verifying [{}]...", save_name);
+                let current = manifest::generate(&noita_save_dir)?;
+                // The manifest of a stored save is made from the hashes of the
+                // original, and files already in the store are only compared
+                // by size, so the copy is hashed again
+                let copied = manifest::generate(&save_dir)?;
+                if let Err(error) = verify_copy(&current, &copied.subtree(&prefix)) {
+                    say!("Failed to verify [{}]: {}", save_name, error);
+                    update_gamedb(|mut gamedb: GameDB| {
+                        gamedb.saves.retain(|item| item.directory != save.directory);
+                        gamedb
+                    })?;
+                    set_save_readonly(&save_dir, save.stored, false)?;
+                    fs::remove_dir_all(&save_dir)?;
+                    say!("The save has been removed, the current save has not been touched");
+                    return Err(error);
+                }
+            }
+            mark_loaded(&save.directory, manifest.digest(&prefix))?;
+        }
+    }
+    say!("Successfully saved game with name [{}]", save_name);
+    if output::is_json() {
+        output::emit(&json!({ "event": "saved", "name": save_name }));
+    }
+    Ok(())
+}
+
+fn parse_interval(interval: &str) -> Result<Duration, String> {
+    let split = interval
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(interval.len());
+    let (amount, unit) = interval.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("expected an interval such as 10m, not {}", interval))?;
+    let seconds = match unit {
+        "s" => amount,
+        "m" | "" => amount * 60,
+        "h" => amount * 60 * 60,
+        _ => return Err(format!("unknown unit {}, use s, m or h", unit)),
+    };
+    if seconds == 0 {
+        return Err("the interval must be longer than 0".to_string());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+// Lets long running commands finish what they are doing when Ctrl+C is pressed
+fn stop_on_ctrl_c() -> Result<(), CliError> {
+    ctrlc::set_handler(|| STOP.store(true, Ordering::Relaxed))
+        .map_err(|error| CliError::Io(Error::other(error)))
+}
+
+// Returns false if Ctrl+C was pressed while waiting
+fn wait(duration: Duration) -> bool {
+    let until = Instant::now() + duration;
+    while Instant::now() < until {
+        if STOP.load(Ordering::Relaxed) {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+    !STOP.load(Ordering::Relaxed)
+}
+
+// Saves the current save whenever it has changed, until interrupted. Autosaves
+// are regular saves, marked as autosaves so only they are rotated.
+fn autosave(config: &Config, every: Duration, keep: usize) -> Result<(), CliError> {
+    stop_on_ctrl_c()?;
+    let interval = match every.as_secs() {
+        seconds if seconds % 3600 == 0 => format!("{}h", seconds / 3600),
+        seconds if seconds % 60 == 0 => format!("{}m", seconds / 60),
+        seconds => format!("{}s", seconds),
+    };
+    say!(
+        "Autosaving every {}, keeping the latest {} autosaves. Press Ctrl+C to stop",
+        interval,
+        keep
+    );
+
+    let noita_save_dir = noita_save_dir(config);
+    let mut last_digest = None;
+    let mut disk_space = DiskSpaceMonitor::default();
+    disk_space.check(config)?;
+    while wait(every) {
+        disk_space.check(config)?;
+        if !noita_save_dir.exists() {
+            say!("No current save found, skipping autosave");
+            continue;
+        }
+        if let Some(problem) = check_current_save(&noita_save_dir)? {
+            say!("Skipping autosave: {}", problem);
+            continue;
+        }
+        let _lock = lock_current_save()?;
+        let digest = manifest::generate(&noita_save_dir)?.digest("");
+        if last_digest.as_ref() == Some(&digest) {
+            continue;
+        }
+        let name = format!("autosave-{}", Local::now().format(BACKUP_NAME_FORMAT));
+        if !save_dir_as_save(config, &load_gamedb()?, &noita_save_dir, &name, None, false)? {
+            continue;
+        }
+        say!("Saved the current save as [{}]", name);
+        last_digest = Some(digest);
+
+        let mut rotated = Vec::new();
+        update_gamedb(|mut gamedb: GameDB| {
+            if let Some(save) = gamedb.saves.iter_mut().find(|save| save.name == name) {
+                save.autosave = true;
+            }
+            let mut autosaves: Vec<Save> = gamedb
+                .saves
+                .iter()
+                .filter(|save| save.autosave)
+                .cloned()
+                .collect();
+            autosaves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+            rotated = autosaves.split_off(keep.min(autosaves.len()));
+            gamedb.saves.retain(|save| {
+                !rotated
+                    .iter()
+                    .any(|rotated| rotated.directory == save.directory)
+            });
+            gamedb
+        })?;
+        for save in rotated {
+            let save_dir = save_path(&save)?;
+            if save_dir.exists() {
+                set_save_readonly(&save_dir, save.stored, false)?;
+                fs::remove_dir_all(save_dir)?;
+            }
+            say!("Deleted the old autosave [{}]", save.name);
+        }
+    }
+    say!("Stopped autosaving");
+    Ok(())
+}
+
+// A volume with less free space than configured, along with what it holds
+struct LowDiskSpace {
+    volume: disk::Volume,
+    holds: Vec<&'static str>,
+}
+
+impl fmt::Display for LowDiskSpace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Low disk space, {} free on [{}] holding {}",
+            human_size(self.volume.free),
+            self.volume.name,
+            self.holds.join(" and ")
+        )
+    }
+}
+
+// Volumes holding the saves or Noita which are low on free space. Volumes
+// whose free space cannot be found are left out.
+fn low_disk_space(config: &Config) -> Result<Vec<LowDiskSpace>, CliError> {
+    let threshold = config.low_disk_space_mib * 1024 * 1024;
+    if threshold == 0 {
+        return Ok(Vec::new());
+    }
+    let mut low: Vec<LowDiskSpace> = Vec::new();
+    for (holds, path) in [
+        ("the saves", nauttaja_dir()?),
+        ("Noita", PathBuf::from(&config.noita_root_dir)),
+    ] {
+        let volume = match disk::volume(&path) {
+            Ok(volume) if volume.free < threshold => volume,
+            _ => continue,
+        };
+        match low.iter_mut().find(|low| low.volume.name == volume.name) {
+            Some(low) => low.holds.push(holds),
+            None => low.push(LowDiskSpace {
+                volume,
+                holds: vec![holds],
+            }),
+        }
+    }
+    Ok(low)
+}
+
+// Warns when a volume runs low on free space and once it has recovered, for
+// commands which keep running. Free space is checked at most once a minute.
+#[derive(Default)]
+struct DiskSpaceMonitor {
+    low: Vec<String>,
+    checked: Option<Instant>,
+}
+
+impl DiskSpaceMonitor {
+    fn check(&mut self, config: &Config) -> Result<(), CliError> {
+        if self
+            .checked
+            .is_some_and(|checked| checked.elapsed() < Duration::from_secs(60))
+        {
+            return Ok(());
+        }
+        self.checked = Some(Instant::now());
+        let low = low_disk_space(config)?;
+        for volume in &low {
+            if !self.low.contains(&volume.volume.name) {
+                say!("{}", volume);
+            }
+        }
+        for name in &self.low {
+            if !low.iter().any(|volume| &volume.volume.name == name) {
+                say!("Disk space on [{}] is no longer low", name);
+            }
+        }
+        self.low = low.into_iter().map(|volume| volume.volume.name).collect();
+        Ok(())
+    }
+}
+
+// Saves the current game every time Noita exits, until interrupted
+fn watch(config: &Config) -> Result<(), CliError> {
+    stop_on_ctrl_c()?;
+    let mut running = process::is_running(process::NOITA_PROCESS)?;
+    say!(
+        "Watching for Noita to exit, {}. Press Ctrl+C to stop",
+        if running {
+            "it is running"
+        } else {
+            "it is not running yet"
+        }
+    );
+    let mut disk_space = DiskSpaceMonitor::default();
+    disk_space.check(config)?;
+    // A session already going is counted from when watching began
+    let mut started = running.then(timestamp);
+    while wait(Duration::from_secs(2)) {
+        disk_space.check(config)?;
+        let was_running = running;
+        running = process::is_running(process::NOITA_PROCESS)?;
+        if running && !was_running {
+            say!("Noita started");
+            started = Some(timestamp());
+        } else if was_running && !running {
+            say!("Noita exited");
+            save_on_exit(config)?;
+            if let Some(started) = started.take() {
+                record_session(config, started)?;
+            }
+        }
+    }
+    say!("Stopped watching");
+    Ok(())
+}
+
+fn save_on_exit(config: &Config) -> Result<(), CliError> {
+    let noita_save_dir = noita_save_dir(config);
+    if !noita_save_dir.exists() {
+        say!("No current save found, nothing to save");
+        return Ok(());
+    }
+    let _lock = lock_current_save()?;
+    let gamedb = load_gamedb()?;
+    if let Some(identical) = find_identical_save(&gamedb, &noita_save_dir)? {
+        say!("The current save is already saved as {}", identical);
+        return Ok(());
+    }
+    let name = generate_name(config, &gamedb, &noita_save_dir, "exit-")?;
+    save_game(config, &gamedb, &name, None, true, false, false)
+}
+
+// Sums up the session in the gamedb, from which journal writes a play diary
+fn record_session(config: &Config, started: String) -> Result<(), CliError> {
+    let noita_save_dir = noita_save_dir(config);
+    let stats = noita::session_stats(&noita_save_dir);
+    let gamedb = load_gamedb()?;
+    let session = Session {
+        ended: timestamp(),
+        seed: stats.as_ref().and_then(|stats| stats.seed.clone()),
+        playtime_secs: stats.as_ref().and_then(|stats| stats.playtime_secs),
+        progress: noita::run_progress(&noita_save_dir),
+        snapshots: gamedb
+            .saves
+            .iter()
+            .chain(gamedb.trash.iter())
+            .filter(|save| save.timestamp >= started)
+            .count(),
+        started,
+    };
+    update_gamedb(|mut gamedb: GameDB| {
+        gamedb.sessions.push(session.clone());
+        gamedb
+    })?;
+
+    let summary = describe_session(&session);
+    say!("Session over: {}", summary);
+    if config.notify_sessions {
+        if let Err(error) = notify::send("Noita session over", &summary) {
+            say!("Failed to show a notification: {}", error);
+        }
+    }
+    Ok(())
+}
+
+// Such as "played 1h 05m of run 123456789, left at depth 2050, Coal Pits, 3 saves made"
+fn describe_session(session: &Session) -> String {
+    let playtime = session.playtime_secs.or_else(|| {
+        let started = parse_timestamp(&session.started)?;
+        let ended = parse_timestamp(&session.ended)?;
+        Some((ended - started).num_seconds() as f64)
+    });
+    let mut parts = vec![match (playtime, &session.seed) {
+        (Some(playtime), Some(seed)) => {
+            format!("played {} of run {}", describe_playtime(playtime), seed)
+        }
+        (Some(playtime), None) => format!("played {}", describe_playtime(playtime)),
+        (None, Some(seed)) => format!("played run {}", seed),
+        (None, None) => "played".to_string(),
+    }];
+    if let Some(progress) = &session.progress {
+        parts.push(format!("left at {}", describe_progress(progress)));
+    }
+    parts.push(match session.snapshots {
+        1 => "1 save made".to_string(),
+        snapshots => format!("{} saves made", snapshots),
+    });
+    parts.join(", ")
+}
+
+// Renames the latest autosave and unmarks it as an autosave, so autosave no
+// longer rotates it. Works while autosave is running, since both go through
+// the gamedb.
+fn checkpoint(label: &str) -> Result<(), CliError> {
+    let mut outcome = None;
+    update_gamedb(|mut gamedb: GameDB| {
+        let taken = gamedb
+            .saves
+            .iter()
+            .chain(gamedb.trash.iter())
+            .any(|save| save.name == label);
+        let latest = gamedb
+            .saves
+            .iter_mut()
+            .filter(|save| save.autosave)
+            .max_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        outcome = Some(match latest {
+            _ if taken => Err(format!("[{}] already exists", label)),
+            None => {
+                Err("No autosaves to keep, autosaves are made by nauttaja autosave".to_string())
+            }
+            Some(save) => {
+                let autosave = std::mem::replace(&mut save.name, label.to_string());
+                save.autosave = false;
+                Ok((autosave, save.timestamp.clone()))
+            }
+        });
+        gamedb
+    })?;
+
+    match outcome.expect("The gamedb was updated") {
+        Ok((autosave, timestamp)) => say!(
+            "Kept the autosave [{}], made {}, as [{}]",
+            autosave,
+            age(&timestamp).unwrap_or(timestamp),
+            label
+        ),
+        Err(message) => say!("{}", message),
+    }
+    Ok(())
+}
+
+// Quicksaves are regular saves, marked with the slot they belong to
+fn quicksave(config: &Config, slot: u32) -> Result<(), CliError> {
+    let name = format!("quicksave-{}", slot);
+    let gamedb = load_gamedb()?;
+    if let Some(save) = gamedb
+        .saves
+        .iter()
+        .chain(gamedb.trash.iter())
+        .find(|save| save.name == name)
+    {
+        if save.slot != Some(slot) {
+            say!(
+                "[{}] already exists and is not a quicksave, rename it first",
+                name
+            );
+            return Ok(());
+        }
+    }
+    save_game(config, &gamedb, &name, None, true, true, false)?;
+    update_gamedb(|mut gamedb: GameDB| {
+        if let Some(save) = gamedb.saves.iter_mut().find(|save| save.name == name) {
+            save.slot = Some(slot);
+        }
+        gamedb
+    })
+}
+
+fn quickload(
+    config: &Config,
+    slot: u32,
+    backup: bool,
+    cross_profile: bool,
+) -> Result<(), CliError> {
+    let gamedb = load_gamedb()?;
+    match gamedb.saves.iter().find(|save| save.slot == Some(slot)) {
+        Some(save) => load_save(config, &save.name, backup, false, cross_profile),
+        None => {
+            say!(
+                "Slot {} is empty, quicksave into it with nauttaja qs {}",
+                slot,
+                slot
+            );
+            Ok(())
+        }
+    }
+}
+
+fn find_identical_save(gamedb: &GameDB, noita_save_dir: &Path) -> Result<Option<String>, CliError> {
+    let digest = manifest::generate(noita_save_dir)?.digest("");
+    let prefix = format!("{}/", NOITA_SAVE_DIRECTORY);
+    for save in gamedb.saves.iter().chain(gamedb.trash.iter()) {
+        if let Some(manifest) = manifest::read(&save_path(save)?)? {
+            if manifest.digest(&prefix) == digest {
+                return Ok(Some(match save.removed {
+                    Some(_) => format!("[{}], currently in the trash", save.name),
+                    None => format!("[{}]", save.name),
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn check_current_save(noita_save_dir: &Path) -> Result<Option<String>, CliError> {
+    if fs::read_dir(noita_save_dir)?.next().is_none() {
+        return Ok(Some("the current save is empty".to_string()));
+    }
+    if let Some(modified) = latest_modification(noita_save_dir)? {
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        if age < Duration::from_secs(SETTLE_TIME_SECS) {
+            return Ok(Some(
+                "the current save was modified moments ago and may still be written to, is Noita running?"
+                    .to_string(),
+            ));
+        }
+    }
+    Ok(None)
+}
+
+// Loading replaces save00, so both Noitas root directory and every directory
+// below save00 must allow files to be created and removed
+fn find_unwritable_dir(config: &Config) -> Result<Option<PathBuf>, CliError> {
+    let root = Path::new(&config.noita_root_dir);
+    if !is_writable(root)? {
+        return Ok(Some(root.to_path_buf()));
+    }
+    let noita_save_dir = noita_save_dir(config);
+    if !noita_save_dir.exists() {
+        return Ok(None);
+    }
+    let mut dirs = vec![noita_save_dir];
+    while let Some(dir) = dirs.pop() {
+        if !is_writable(&dir)? {
+            return Ok(Some(dir));
+        }
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn is_writable(dir: &Path) -> Result<bool, CliError> {
+    let probe = dir.join(".nauttaja-write-test");
+    match fs::File::create(&probe) {
+        Ok(_) => {
+            fs::remove_file(probe)?;
+            Ok(true)
+        }
+        Err(error)
+            if matches!(
+                error.kind(),
+                ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn latest_modification(path: &Path) -> io::Result<Option<SystemTime>> {
+    let mut latest = None;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let modified = if entry.file_type()?.is_dir() {
+            latest_modification(&entry.path())?
+        } else {
+            Some(entry.metadata()?.modified()?)
+        };
+        latest = latest.max(modified);
+    }
+    Ok(latest)
+}
+
+fn save_dir_as_save<T>(
+    config: &Config,
+    gamedb: &GameDB,
+    directory: T,
+    save_name: &str,
+    note: Option<&str>,
+    overwrite: bool,
+) -> Result<bool, CliError>
+where
+    T: AsRef<Path>,
+{
+    let existing = gamedb.saves.iter().find(|item| item.name == save_name);
+    if existing.is_some() && !overwrite {
+        say!("[{}] already exists", save_name);
+        say!("Run the command again with --overwrite to replace it");
+        return Ok(false);
+    }
+    if gamedb.trash.iter().any(|item| item.name == save_name) {
+        say!("[{}] already exists, currently in the trash", save_name);
+        return Ok(false);
+    }
+    let replaced = existing.cloned();
+
+    let work_dir = nauttaja_dir()?;
+    let from_install = directory.as_ref() == noita_save_dir(config);
+    let work = estimate::Work::of_dir(directory.as_ref())?;
+    // A save being replaced is brought up to date in its own directory, only
+    // copying the files which changed since, unless its files are in the store
+    let updated_dir = match &replaced {
+        Some(replaced) if !replaced.stored && !config.dedup_saves => {
+            Some(save_path(replaced)?).filter(|dir| dir.exists())
+        }
+        _ => None,
+    };
+    if dry_run() {
+        if let Some(updated_dir) = &updated_dir {
+            say!(
+                "Would update [{}] from [{}], copying only the files which changed",
+                updated_dir.display(),
+                directory.as_ref().display()
+            );
+            say!("Would replace [{}] in the gamedb", save_name);
+            return Ok(false);
+        }
+        say!(
+            "Would copy [{}] to a new directory in [{}], {}",
+            directory.as_ref().display(),
+            work_dir.join(NAUTTAJA_SAVES_DIRECTORY).display(),
+            estimate::describe("save", work)
+        );
+        match &replaced {
+            Some(replaced) => {
+                say!("Would replace [{}] in the gamedb", save_name);
+                say!("Would delete [{}]", save_path(replaced)?.display());
+            }
+            None => say!("Would add [{}] to the gamedb", save_name),
+        }
+        return Ok(false);
+    }
+    let (save_dir_name, save_dir) = match (&replaced, &updated_dir) {
+        (Some(replaced), Some(updated_dir)) => (replaced.directory.clone(), updated_dir.clone()),
+        _ => {
+            let save_dir_name = uuid();
+            let save_dir = work_dir.join(NAUTTAJA_SAVES_DIRECTORY).join(&save_dir_name);
+            (save_dir_name, save_dir)
+        }
+    };
+
+    fs::create_dir_all(save_dir.clone())?;
+
+    let started = Instant::now();
+    let manifest = if updated_dir.is_some() {
+        // Until the manifest is written again below, the save no longer
+        // matches it, so a save left half updated is refused by load
+        copy::set_readonly(&save_dir, false)?;
+        let from = directory.as_ref();
+        let stats = copy::mirror_dir(
+            "copy",
+            from,
+            &save_dir.join(from.file_name().unwrap_or_default()),
+        )?;
+        info!(
+            "Updated [{}] from [{}] in {:.2?}, copying {} changed files and skipping {} unchanged files",
+            save_dir.display(),
+            from.display(),
+            started.elapsed(),
+            stats.copied,
+            stats.skipped
+        );
+        manifest::generate(&save_dir)?
+    } else {
+        if estimate::is_long("save", work) {
+            say!("Copying {}...", estimate::describe("save", work));
+        }
+        let manifest = if config.dedup_saves {
+            store_copy(directory.as_ref(), &save_dir)?
+        } else {
+            copy_with(&noita_chain(config), directory.as_ref(), &save_dir)?;
+            manifest::generate(&save_dir)?
+        };
+        estimate::record("save", work, started.elapsed());
+        manifest
+    };
+    manifest::write(&save_dir, &manifest)?;
+    // Compressing would take the files out of the store
+    if config.compress_saves && !config.dedup_saves {
+        compress_world(&save_dir, save_name);
+    }
+    if config.immutable_saves {
+        set_save_readonly(&save_dir, config.dedup_saves, true)?;
+    }
+
+    update_gamedb(|mut gamedb: GameDB| {
+        let save = Save {
+            name: save_name.to_string(),
+            directory: save_dir_name.clone(),
+            timestamp: timestamp(),
+            size: Some(manifest.files.iter().map(|file| file.size).sum()),
+            files: Some(manifest.files.len() as u64),
+            note: note
+                .map(str::to_string)
+                .or_else(|| replaced.as_ref().and_then(|save| save.note.clone())),
+            index: Some(noita::index(&save_dir.join(NOITA_SAVE_DIRECTORY))),
+            created_by: Some(crate_version!().to_string()),
+            noita_root_dir: from_install.then(|| config.noita_root_dir.clone()),
+            stored: config.dedup_saves,
+            ..Default::default()
+        };
+        gamedb.saves.retain(|item| item.name != save_name);
+        gamedb.saves.push(save);
+        if let Some(loaded) = &gamedb.loaded {
+            if replaced
+                .as_ref()
+                .is_some_and(|save| save.directory == loaded.directory)
+            {
+                gamedb.loaded = None;
+            }
+        }
+        gamedb
+    })?;
+
+    if let Some(replaced) = replaced {
+        let old_dir = save_path(&replaced)?;
+        if updated_dir.is_none() && old_dir.exists() {
+            set_save_readonly(&old_dir, replaced.stored, false)?;
+            info!("Deleting [{}]", old_dir.display());
+            fs::remove_dir_all(old_dir)?;
+        }
+        say!("Replaced the previous save with name [{}]", save_name);
+    }
+    Ok(true)
+}
+
+fn load_save(
+    config: &Config,
+    save_name: &str,
+    backup: bool,
+    verify: bool,
+    cross_profile: bool,
+) -> Result<(), CliError> {
+    say!("Loading save with name [{}]", save_name);
+
+    let work_dir = nauttaja_dir()?;
+    let gamedb = load_gamedb()?;
+    let save = gamedb.saves.iter().find(|item| item.name == save_name);
+    if save.is_none() {
+        // A single close match is offered in place of the name, though not
+        // with --yes, which would load a save that was never named
+        if !assume_yes() {
+            if let [suggestion] = closest_names(save_name, &gamedb.saves).as_slice() {
+                if confirm(&format!(
+                    "Failed to find [{}], did you mean [{}]?",
+                    save_name, suggestion
+                ))? {
+                    return load_save(config, suggestion, backup, verify, cross_profile);
+                }
+                say!("Aborted, the current save has not been touched");
+                return Ok(());
+            }
+        }
+        say!("{}", not_found(save_name, &gamedb.saves));
+        return Ok(());
+    }
+    let save = save.unwrap();
+    let save_dir = work_dir
+        .join(NAUTTAJA_SAVES_DIRECTORY)
+        .join(save.directory.clone());
+
+    if !save_dir.exists() {
+        say!("Failed to find save with name [{}]", save_name);
+        return Ok(());
+    }
+
+    if let Some(origin) = save
+        .noita_root_dir
+        .as_deref()
+        .filter(|origin| !cross_profile && !same_install(origin, &config.noita_root_dir))
+    {
+        say!(
+            "Refusing to load [{}]: it was saved from the Noita install at [{}], not [{}]",
+            save_name,
+            origin,
+            config.noita_root_dir
+        );
+        say!("The current save has not been touched");
+        say!("Run the command again with --cross-profile to load it anyway");
+        return Ok(());
+    }
+
+    if let Some(version) = save
+        .created_by
+        .as_deref()
+        .filter(|version| is_newer(version))
+    {
+        say!(
+            "Warning: [{}] was created by nauttaja {}, which is newer than this version ({})",
+            save_name,
+            version,
+            crate_version!()
+        );
+    }
+
+    say!("Validating [{}]...", save_name);
+    if let Some(problem) = validate_save(&save_dir)? {
+        say!("Refusing to load [{}]: {}", save_name, problem);
+        say!("The current save has not been touched");
+        return Ok(());
+    }
+
+    if let Some(dir) = find_unwritable_dir(config)? {
+        say!(
+            "Refusing to load [{}]: [{}] is not writable",
+            save_name,
+            dir.display()
+        );
+        if elevation::available() && confirm("Run the command again as administrator?")? {
+            say!("Running nauttaja as administrator in a new window...");
+            return elevation::relaunch();
+        }
+        say!("The current save has not been touched. To fix this, either");
+        say!("- make sure your user is allowed to write to the directory and that it is not read-only");
+        say!("- run nauttaja as administrator, if Noita is installed in a protected location such as Program Files");
+        say!("- move Noita to a directory you own, and point nauttaja to it with nauttaja set-noita-dir");
+        return Ok(());
+    }
+
+    if dry_run() {
+        return load_plan(config, &gamedb, save, &save_dir, backup);
+    }
+
+    if !steam_cloud::check_before_load(config)? {
+        say!("Aborted, the current save has not been touched");
+        return Ok(());
+    }
+
+    let had_current_save = noita_save_dir(config).exists();
+    // Without a backup, losing the current save is confirmed below instead
+    if had_current_save
+        && backup
+        && !config.pre_load_snapshot
+        && find_identical_save(&gamedb, &noita_save_dir(config))?.is_none()
+        && !confirm(
+            "The current save has not been saved with nauttaja and will be replaced, continue?",
+        )?
+    {
+        say!("Aborted, the current save has not been touched");
+        return Ok(());
+    }
+    if had_current_save && config.pre_load_snapshot {
+        pre_load_snapshot(config, &gamedb)?;
+    }
+    let take_backup = had_current_save
+        && if !backup {
+            say!("Warning: no emergency backup will be created");
+            if !confirm("The current save will be lost for good, continue?")? {
+                say!("Aborted, the current save has not been touched");
+                return Ok(());
+            }
+            false
+        } else {
+            match config.backup {
+                BackupPolicy::Always => true,
+                // --yes goes ahead with the backup rather than skipping it
+                BackupPolicy::Ask => {
+                    assume_yes() || !confirm("Skip the emergency backup of the current save?")?
+                }
+                BackupPolicy::Never => false,
+            }
+        };
+
+    let backup_dir = if take_backup {
+        say!("Creating emergency backup...");
+        let (backup_dir, stats) = create_backup(config)?;
+        say!(
+            "Backed up {} changed files, skipped {} unchanged files",
+            stats.copied,
+            stats.skipped
+        );
+        Some(backup_dir)
+    } else {
+        if had_current_save {
+            say!("Skipping emergency backup");
+        } else {
+            say!("No current save found, skipping emergency backup");
+        }
+        None
+    };
+
+    let expected = if verify {
+        Some(match manifest::read(&save_dir)? {
+            Some(manifest) => manifest.subtree(&format!("{}/", NOITA_SAVE_DIRECTORY)),
+            None => manifest::generate(&save_dir.join(NOITA_SAVE_DIRECTORY))?,
+        })
+    } else {
+        None
+    };
+
+    let _lock = lock_current_save()?;
+    let work = load_work(save, &save_dir)?;
+    if estimate::is_long("load", work) {
+        say!(
+            "Loading [{}], {}...",
+            save_name,
+            estimate::describe("load", work)
+        );
+    } else {
+        say!("Loading [{}]...", save_name);
+    }
+    let started = Instant::now();
+    let result = if had_current_save {
+        info!("Deleting [{}]", noita_save_dir(config).display());
+        fs::remove_dir_all(noita_save_dir(config)).map_err(CliError::from)
+    } else {
+        Ok(())
+    }
+    .and_then(|_| copy_into_noita(config, save_dir.join(NOITA_SAVE_DIRECTORY)))
+    .and_then(|_| copy::set_readonly(&noita_save_dir(config), false))
+    .and_then(|_| compress::decompress(&noita_save_dir(config).join(NOITA_WORLD_DIRECTORY)))
+    .and_then(|_| manifest::generate(&noita_save_dir(config)))
+    .and_then(|loaded| {
+        if let Some(expected) = &expected {
+            say!("Verifying [{}]...", save_name);
+            verify_copy(expected, &loaded)?;
+        }
+        Ok(loaded)
+    });
+
+    let loaded = match result {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            say!("Failed to load [{}]: {}", save_name, error);
+            if !had_current_save {
+                say!("There was no previous save, removing what was partially loaded...");
+                let noita_save_dir = noita_save_dir(config);
+                if noita_save_dir.exists() {
+                    fs::remove_dir_all(noita_save_dir)?;
+                }
+                return Err(error);
+            }
+            let backup_dir = match backup_dir {
+                Some(backup_dir) => backup_dir,
+                None => {
+                    say!("No emergency backup was created, the previous save cannot be restored");
+                    return Err(error);
+                }
+            };
+            say!("Rolling back to the emergency backup...");
+            if let Err(rollback_error) = rollback_load(config, &backup_dir) {
+                say!("Failed to roll back: {}", rollback_error);
+                say!(
+                    "Your previous save is still available at [{}]",
+                    backup_dir.join(NOITA_SAVE_DIRECTORY).display()
+                );
+                return Err(rollback_error);
+            }
+            say!("Rolled back successfully, the previous save has been restored");
+            return Err(error);
+        }
+    };
+
+    estimate::record("load", work, started.elapsed());
+    mark_loaded(&save.directory, loaded.digest(""))?;
+    count_load(&save.directory)?;
+    say!("Save [{}] successfully loaded!", save_name);
+    if output::is_json() {
+        output::emit(&json!({ "event": "loaded", "name": save_name }));
+    }
+    Ok(())
+}
+
+// Tells what loading would do, asking nothing along the way
+fn load_plan(
+    config: &Config,
+    gamedb: &GameDB,
+    save: &Save,
+    save_dir: &Path,
+    backup: bool,
+) -> Result<(), CliError> {
+    let noita_save_dir = noita_save_dir(config);
+    if noita_save_dir.exists() {
+        let identical = find_identical_save(gamedb, &noita_save_dir)?;
+        if config.pre_load_snapshot {
+            match identical {
+                Some(identical) => say!(
+                    "The current save is identical to {}, no pre-load snapshot would be made",
+                    identical
+                ),
+                None => {
+                    let name = generate_name(config, gamedb, &noita_save_dir, "pre-load-")?;
+                    say!("Would save the current save as [{}]", name);
+                    save_dir_as_save(config, gamedb, &noita_save_dir, &name, None, false)?;
+                }
+            }
+        } else if backup && identical.is_none() {
+            say!("Would ask before replacing the current save, which has not been saved with nauttaja");
+        }
+        let backup_dir = nauttaja_dir()?
+            .join(NAUTTAJA_LAST_REPLACED_DIRECTORY)
+            .join(Local::now().format(BACKUP_NAME_FORMAT).to_string());
+        match (backup, &config.backup) {
+            (false, _) => say!(
+                "Would ask before losing the current save for good, without an emergency backup"
+            ),
+            (true, BackupPolicy::Always) => say!(
+                "Would back up [{}] to [{}]",
+                noita_save_dir.display(),
+                backup_dir.display()
+            ),
+            (true, BackupPolicy::Ask) => say!(
+                "Would ask whether to back up [{}] to [{}]",
+                noita_save_dir.display(),
+                backup_dir.display()
+            ),
+            (true, BackupPolicy::Never) => say!("Would not make an emergency backup"),
+        }
+        say!("Would delete [{}]", noita_save_dir.display());
+    }
+    say!(
+        "Would copy [{}] to [{}], {}",
+        save_dir.join(NOITA_SAVE_DIRECTORY).display(),
+        config.noita_root_dir,
+        estimate::describe("load", load_work(save, save_dir)?)
+    );
+    if compress::is_compressed(
+        &save_dir
+            .join(NOITA_SAVE_DIRECTORY)
+            .join(NOITA_WORLD_DIRECTORY),
+    )? {
+        say!(
+            "Would decompress the world in [{}]",
+            noita_save_dir.join(NOITA_WORLD_DIRECTORY).display()
+        );
+    }
+    say!("Would mark [{}] as loaded in the gamedb", save.name);
+    Ok(())
+}
+
+// What loading the save gets through, as cached in the gamedb when known
+fn load_work(save: &Save, save_dir: &Path) -> Result<estimate::Work, CliError> {
+    match (save.size, save.files) {
+        (Some(bytes), Some(files)) => Ok(estimate::Work { bytes, files }),
+        _ => estimate::Work::of_dir(&save_dir.join(NOITA_SAVE_DIRECTORY)),
+    }
+}
+
+fn pre_load_snapshot(config: &Config, gamedb: &GameDB) -> Result<(), CliError> {
+    let noita_save_dir = noita_save_dir(config);
+    if let Some(identical) = find_identical_save(gamedb, &noita_save_dir)? {
+        say!(
+            "The current save is identical to {}, skipping the pre-load snapshot",
+            identical
+        );
+        return Ok(());
+    }
+    let name = generate_name(config, gamedb, &noita_save_dir, "pre-load-")?;
+    say!("Saving the current save as [{}]", name);
+    save_dir_as_save(config, gamedb, &noita_save_dir, &name, None, false)?;
+    Ok(())
+}
+
+// Fills in the name template from the save, adding a number if the name is taken
+fn generate_name(
+    config: &Config,
+    gamedb: &GameDB,
+    save00: &Path,
+    prefix: &str,
+) -> Result<String, CliError> {
+    let progress = noita::run_progress(save00);
+    let stats = noita::session_stats(save00);
+    let now = Local::now();
+    let mut name = prefix.to_string();
+    let mut rest = config.name_template.as_str();
+    while let Some(start) = rest.find('{') {
+        // The template is validated when the config is loaded
+        let end = start + rest[start..].find('}').expect("Validated template");
+        name.push_str(&rest[..start]);
+        let value = match &rest[start + 1..end] {
+            "seed" => stats.as_ref().and_then(|stats| stats.seed.clone()),
+            "depth" => progress
+                .as_ref()
+                .map(|progress| format!("{:.0}", progress.depth)),
+            "biome" => progress
+                .as_ref()
+                .map(|progress| progress.biome.to_lowercase().replace(' ', "-")),
+            "orbs" => progress
+                .as_ref()
+                .and_then(|progress| progress.orbs)
+                .map(|orbs| orbs.to_string()),
+            "date" => Some(now.format("%Y-%m-%d").to_string()),
+            "time" => Some(now.format("%H%M%S").to_string()),
+            _ => None,
+        };
+        name.push_str(value.as_deref().unwrap_or("unknown"));
+        rest = &rest[end + 1..];
+    }
+    name.push_str(rest);
+
+    Ok(unique_name(gamedb, &name))
+}
+
+// Adds a number to the name if it is already taken
+fn unique_name(gamedb: &GameDB, name: &str) -> String {
+    let taken = |name: &str| {
+        gamedb
+            .saves
+            .iter()
+            .chain(gamedb.trash.iter())
+            .any(|save| save.name == name)
+    };
+    if !taken(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|number| format!("{}-{}", name, number))
+        .find(|name| !taken(name))
+        .expect("Some number is free")
+}
+
+// The saves of a run share the seed of the current save. Stepping back starts
+// from the save the current save was loaded from, or saved as, which counts as
+// a step of its own once the current save has moved on from it.
+fn rollback(
+    config: &Config,
+    steps: usize,
+    backup: bool,
+    cross_profile: bool,
+) -> Result<(), CliError> {
+    if steps == 0 {
+        say!("Please specify at least 1 step");
+        return Ok(());
+    }
+    let noita_save_dir = noita_save_dir(config);
+    let seed = match noita::session_stats(&noita_save_dir).and_then(|stats| stats.seed) {
+        Some(seed) => seed,
+        None => {
+            say!("Failed to tell which run the current save belongs to");
+            return Ok(());
+        }
+    };
+    let gamedb = load_gamedb_indexed()?;
+    let mut run: Vec<&Save> = gamedb
+        .saves
+        .iter()
+        .filter(|save| {
+            save.index
+                .as_ref()
+                .and_then(|index| index.stats.as_ref())
+                .and_then(|stats| stats.seed.as_deref())
+                == Some(seed.as_str())
+        })
+        .collect();
+    // Saves made within the same second are ordered as they were added
+    run.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    run.reverse();
+
+    let target = match gamedb.loaded.as_ref().and_then(|loaded| {
+        run.iter()
+            .position(|save| save.directory == loaded.directory)
+            .map(|position| (position, loaded))
+    }) {
+        Some((position, loaded)) => {
+            if manifest::generate(&noita_save_dir)?.digest("") == loaded.digest {
+                position + steps
+            } else {
+                position + steps - 1
+            }
+        }
+        None => steps - 1,
+    };
+    let save = match run.get(target) {
+        Some(save) => save,
+        None => {
+            say!(
+                "The current run has no save {} back, see nauttaja journal {}",
+                describe_steps(steps),
+                seed
+            );
+            return Ok(());
+        }
+    };
+    say!("Rolling back {} to [{}]", describe_steps(steps), save.name);
+    load_save(config, &save.name, backup, false, cross_profile)
+}
+
+fn describe_steps(steps: usize) -> String {
+    match steps {
+        1 => "1 step".to_string(),
+        steps => format!("{} steps", steps),
+    }
+}
+
+fn mark_loaded(directory: &str, digest: String) -> Result<(), CliError> {
+    update_gamedb(|mut gamedb: GameDB| {
+        gamedb.loaded = Some(Loaded {
+            directory: directory.to_string(),
+            timestamp: timestamp(),
+            digest: digest.clone(),
+        });
+        gamedb
+    })
+}
+
+fn count_load(directory: &str) -> Result<(), CliError> {
+    update_gamedb(|mut gamedb: GameDB| {
+        if let Some(save) = gamedb
+            .saves
+            .iter_mut()
+            .find(|save| save.directory == directory)
+        {
+            save.loads = Some(save.loads.unwrap_or(0) + 1);
+            save.last_loaded = Some(timestamp());
+        }
+        gamedb
+    })
+}
+
+// Fails unless the copy holds exactly the files of the original
+fn verify_copy(original: &manifest::Manifest, copy: &manifest::Manifest) -> Result<(), CliError> {
+    let verification = manifest::compare(original, copy);
+    if verification.is_ok() {
+        return Ok(());
+    }
+    Err(CliError::Io(Error::new(
+        ErrorKind::InvalidData,
+        format!(
+            "the copy does not match the original ({} missing, {} changed, {} unexpected)",
+            verification.missing.len(),
+            verification.changed.len(),
+            verification.unexpected.len()
+        ),
+    )))
+}
+
+fn validate_save(save_dir: &Path) -> Result<Option<String>, CliError> {
+    let snapshot = save_dir.join(NOITA_SAVE_DIRECTORY);
+    if !snapshot.is_dir() {
+        return Ok(Some(format!(
+            "the snapshot does not contain a {} directory",
+            NOITA_SAVE_DIRECTORY
+        )));
+    }
+    if fs::read_dir(&snapshot)?.next().is_none() {
+        return Ok(Some("the snapshot is empty".to_string()));
+    }
+    if snapshot.join(NOITA_WORLD_DIRECTORY).is_dir() {
+        let missing: Vec<&str> = NOITA_RUN_FILES
+            .iter()
+            .copied()
+            .filter(|file| !snapshot.join(file).is_file())
+            .collect();
+        if !missing.is_empty() {
+            return Ok(Some(format!(
+                "the snapshot contains a run but is missing {}",
+                missing.join(", ")
+            )));
+        }
+    }
+
+    let expected = match manifest::read(save_dir)? {
+        Some(expected) => expected,
+        None => {
+            say!("No manifest found, skipping verification of file contents");
+            return Ok(None);
+        }
+    };
+    let actual = generate_manifests(&[save_dir.to_path_buf()])
+        .pop()
+        .expect("One manifest per root")?;
+    let verification = manifest::compare(&expected, &actual);
+    if verification.is_ok() {
+        Ok(None)
+    } else {
+        Ok(Some(format!(
+            "the files do not match the manifest ({} missing, {} changed, {} unexpected)",
+            verification.missing.len(),
+            verification.changed.len(),
+            verification.unexpected.len()
+        )))
+    }
+}
+
+fn create_backup(config: &Config) -> Result<(PathBuf, copy::MirrorStats), CliError> {
+    let backups_dir = nauttaja_dir()?.join(NAUTTAJA_LAST_REPLACED_DIRECTORY);
+    fs::create_dir_all(&backups_dir)?;
+
+    // Backups used to be stored directly in the backup directory
+    let legacy_backup = backups_dir.join(NOITA_SAVE_DIRECTORY);
+    if legacy_backup.exists() {
+        let modified: DateTime<Local> = fs::metadata(&legacy_backup)?.modified()?.into();
+        let legacy_dir = backups_dir.join(modified.format(BACKUP_NAME_FORMAT).to_string());
+        fs::create_dir_all(&legacy_dir)?;
+        fs::rename(&legacy_backup, legacy_dir.join(NOITA_SAVE_DIRECTORY))?;
+    }
+
+    let mut existing = list_backups()?;
+    let retention = config.backup_retention.max(1);
+    let backup_dir = backups_dir.join(Local::now().format(BACKUP_NAME_FORMAT).to_string());
+    if !backup_dir.exists() && existing.len() >= retention {
+        // Recycle the oldest backup, so files which have not changed need not be copied again
+        fs::rename(existing.remove(0), &backup_dir)?;
+    }
+    existing.retain(|dir| *dir != backup_dir);
+    while existing.len() >= retention {
+        fs::remove_dir_all(existing.remove(0))?;
+    }
+
+    let started = Instant::now();
+    let stats = copy::mirror_dir(
+        "backup",
+        &noita_save_dir(config),
+        &backup_dir.join(NOITA_SAVE_DIRECTORY),
+    )?;
+    fault::check("backup")?;
+    info!(
+        "Mirrored [{}] into [{}] in {:.2?}, removing {} stale entries",
+        noita_save_dir(config).display(),
+        backup_dir.display(),
+        started.elapsed(),
+        stats.removed
+    );
+    Ok((backup_dir, stats))
+}
+
+fn list_backups() -> Result<Vec<PathBuf>, CliError> {
+    let backups_dir = nauttaja_dir()?.join(NAUTTAJA_LAST_REPLACED_DIRECTORY);
+    if !backups_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(backups_dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() && entry.file_name() != NOITA_SAVE_DIRECTORY {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+fn rollback_load(config: &Config, backup_dir: &Path) -> Result<(), CliError> {
+    let noita_save_dir = noita_save_dir(config);
+    if noita_save_dir.exists() {
+        fs::remove_dir_all(&noita_save_dir)?;
+    }
+    fault::check("rollback")?;
+    copy_into_noita(config, backup_dir.join(NOITA_SAVE_DIRECTORY))?;
+    copy::set_readonly(&noita_save_dir, false)
+}
+
+fn verify_saves(save_name: Option<&str>, summary: bool) -> Result<(), CliError> {
+    let gamedb = load_gamedb()?;
+    let saves: Vec<&Save> = gamedb
+        .saves
+        .iter()
+        .chain(gamedb.trash.iter())
+        .filter(|save| save_name.is_none_or(|name| save.name == name))
+        .collect();
+
+    if saves.is_empty() {
+        match save_name {
+            Some(name) => say!(
+                "{}",
+                not_found(name, gamedb.saves.iter().chain(gamedb.trash.iter()))
+            ),
+            None => say!("No saves found"),
+        }
+        return Ok(());
+    }
+    verify(&saves, summary)?;
+    Ok(())
+}
+
+// Verifies the saves and records their health, returning how many have problems
+fn verify(saves: &[&Save], summary: bool) -> Result<usize, CliError> {
+    let mut expected = Vec::with_capacity(saves.len());
+    let mut roots = Vec::new();
+    for save in saves {
+        let root = save_path(save)?;
+        let manifest = if root.exists() {
+            manifest::read(&root)
+        } else {
+            Ok(None)
+        };
+        if let Ok(Some(_)) = manifest {
+            roots.push(root.clone());
+        }
+        expected.push((root, manifest));
+    }
+
+    let mut actual = generate_manifests(&roots).into_iter();
+
+    // Details are printed as they are found, unless only a summary is wanted
+    let report = |message: String| {
+        if !summary {
+            say!("{}", message);
+        }
+    };
+    let mut results = Vec::with_capacity(saves.len());
+    for (save, (root, manifest)) in saves.iter().zip(expected) {
+        let manifest = match manifest {
+            Ok(Some(manifest)) => manifest,
+            Ok(None) if !root.exists() => {
+                report(format!("[{}] Save directory is missing", save.name));
+                results.push((*save, Health::MissingFiles));
+                continue;
+            }
+            Ok(None) => {
+                report(format!("[{}] No manifest, cannot verify", save.name));
+                results.push((*save, Health::NoManifest));
+                continue;
+            }
+            Err(error) => {
+                report(format!(
+                    "[{}] Failed to read manifest: {}",
+                    save.name, error
+                ));
+                results.push((*save, Health::Error));
+                continue;
+            }
+        };
+        let verification = match actual.next().expect("One manifest per root") {
+            Ok(current) => manifest::compare(&manifest, &current),
+            Err(error) => {
+                report(format!("[{}] Failed to hash files: {}", save.name, error));
+                results.push((*save, Health::Error));
+                continue;
+            }
+        };
+        if verification.is_ok() {
+            report(format!("[{}] OK", save.name));
+            results.push((*save, Health::Ok));
+            continue;
+        }
+        if verification.missing.is_empty() {
+            results.push((*save, Health::HashMismatch));
+        } else {
+            results.push((*save, Health::MissingFiles));
+        }
+        report(format!(
+            "[{}] {} missing, {} changed, {} unexpected files",
+            save.name,
+            verification.missing.len(),
+            verification.changed.len(),
+            verification.unexpected.len()
+        ));
+        for path in &verification.missing {
+            report(format!("    missing: {}", path));
+        }
+        for path in &verification.changed {
+            report(format!("    changed: {}", path));
+        }
+        for path in &verification.unexpected {
+            report(format!("    unexpected: {}", path));
+        }
+    }
+
+    update_gamedb(|mut gamedb: GameDB| {
+        for save in gamedb.saves.iter_mut().chain(gamedb.trash.iter_mut()) {
+            if let Some((_, health)) = results
+                .iter()
+                .find(|(verified, _)| verified.directory == save.directory)
+            {
+                save.health = Some(*health);
+                save.verified = Some(timestamp());
+            }
+        }
+        gamedb
+    })?;
+
+    if summary {
+        for (save, health) in &results {
+            let removed = if save.removed.is_some() {
+                " (removed)"
+            } else {
+                ""
+            };
+            say!("{:<14} {}{}", health.describe(), save.name, removed);
+        }
+        say!();
+        for health in &[
+            Health::Ok,
+            Health::MissingFiles,
+            Health::HashMismatch,
+            Health::NoManifest,
+            Health::Error,
+        ] {
+            let count = results.iter().filter(|(_, other)| other == health).count();
+            if count > 0 {
+                say!("{:<14} {} saves", health.describe(), count);
+            }
+        }
+    }
+    let problems = results
+        .iter()
+        .filter(|(_, health)| health.is_unhealthy())
+        .count();
+    say!("Verified {} saves, {} with problems", saves.len(), problems);
+    Ok(problems)
+}
+
+#[derive(Serialize, Deserialize)]
+struct MaintenancePass {
+    started: String,
+    done: Vec<String>,
+}
+
+const MAINTENANCE_STEPS: &[&str] = &["backup", "purge", "store", "index", "scrub"];
+
+// Runs every maintenance step in turn. The steps done so far are recorded, so a
+// pass which is interrupted is resumed by running the command again.
+fn maintain(config: &Config, scrub: Option<usize>) -> Result<(), CliError> {
+    let state_file = nauttaja_dir()?.join(NAUTTAJA_MAINTENANCE_FILE);
+    let mut pass = match fs::read_to_string(&state_file) {
+        Ok(state) => {
+            let pass: MaintenancePass = serde_json::from_str(&state)?;
+            say!("Resuming the maintenance pass started {}", pass.started);
+            pass
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => MaintenancePass {
+            started: timestamp(),
+            done: Vec::new(),
+        },
+        Err(error) => return Err(error.into()),
+    };
+
+    let mut report = Vec::new();
+    for step in MAINTENANCE_STEPS {
+        if pass.done.iter().any(|done| done == step) {
+            report.push((*step, "done in an earlier run".to_string()));
+            continue;
+        }
+        say!("Running [{}]...", step);
+        let outcome = match *step {
+            "backup" => match gamedb::backup()? {
+                Some(backup) => format!("gamedb copied to [{}]", backup.display()),
+                None => "no gamedb to back up".to_string(),
+            },
+            "purge" => {
+                let purged = pending_delete::purge_expired(config)?;
+                format!(
+                    "{} expired deleted saves purged, {} still within their grace period",
+                    purged,
+                    pending_delete::pending_count()?
+                )
+            }
+            "store" => {
+                let (removed, freed) = collect_store_garbage()?;
+                format!(
+                    "{} files no save has removed from the store, {} freed",
+                    removed,
+                    human_size(freed)
+                )
+            }
+            "index" => {
+                let mut gamedb = load_gamedb()?;
+                format!("{} saves indexed", index_saves(&mut gamedb, false)?)
+            }
+            "scrub" => {
+                let gamedb = load_gamedb()?;
+                let mut saves: Vec<&Save> =
+                    gamedb.saves.iter().chain(gamedb.trash.iter()).collect();
+                // Saves which were never verified sort first
+                saves.sort_by(|a, b| a.verified.cmp(&b.verified));
+                let count = scrub.unwrap_or_else(|| saves.len().div_ceil(4));
+                saves.truncate(count);
+                if saves.is_empty() {
+                    "no saves to verify".to_string()
+                } else {
+                    let problems = verify(&saves, true)?;
+                    format!("{} saves verified, {} with problems", saves.len(), problems)
+                }
+            }
+            _ => unreachable!("Unknown maintenance step"),
+        };
+        report.push((*step, outcome));
+        pass.done.push(step.to_string());
+        fs::write(&state_file, serde_json::to_string_pretty(&pass)?)?;
+    }
+    fs::remove_file(&state_file)?;
+
+    if output::is_json() {
+        let report: Vec<Value> = report
+            .into_iter()
+            .map(|(step, outcome)| json!({ "step": step, "outcome": outcome }))
+            .collect();
+        output::emit(&json!({ "started": pass.started, "report": report }));
+        return Ok(());
+    }
+    say!();
+    say!("Maintenance report, pass started {}", pass.started);
+    for (step, outcome) in report {
+        say!("{:<8} {}", step, outcome);
+    }
+    Ok(())
+}
+
+fn bench(config: &Config) -> Result<(), CliError> {
+    let noita_save_dir = noita_save_dir(config);
+    if !noita_save_dir.exists() {
+        say!(
+            "Failed to find the current save at [{}]",
+            noita_save_dir.display()
+        );
+        return Ok(());
+    }
+
+    let content = dir::get_dir_content(&noita_save_dir)?;
+    say!(
+        "Benchmarking with the current save ({} files, {})",
+        content.files.len(),
+        human_size(content.dir_size)
+    );
+
+    let bench_dir = nauttaja_dir()?.join(NAUTTAJA_BENCH_DIRECTORY);
+    for engine in copy::ENGINES {
+        if engine.shares_files() {
+            say!(
+                "{:<10} is never used to copy to or from Noita",
+                engine.name()
+            );
+            continue;
+        }
+        let (snapshot_dir, restore_dir) = reset_bench_dir(&bench_dir, false)?;
+
+        // Snapshots go to the saves directory, restores back to Noita
+        if !engine.is_supported(&noita_save_dir, &snapshot_dir)
+            || !engine.is_supported(&snapshot_dir, &noita_save_dir)
+        {
+            say!(
+                "{:<10} does not work between Noita and the saves",
+                engine.name()
+            );
+            continue;
+        }
+
+        let snapshot_secs = timed(|| engine.copy(&noita_save_dir, &snapshot_dir))?;
+        let restore_secs =
+            timed(|| engine.copy(&snapshot_dir.join(NOITA_SAVE_DIRECTORY), &restore_dir))?;
+        say_bench(engine.name(), content.dir_size, snapshot_secs, restore_secs);
+    }
+
+    // Saves made with compress_saves are copied and then compressed, and those
+    // made with dedup_saves go through the store instead
+    let chain = noita_chain(config);
+    let (snapshot_dir, restore_dir) = reset_bench_dir(&bench_dir, false)?;
+    let snapshot_secs = timed(|| {
+        copy_with(&chain, &noita_save_dir, &snapshot_dir)?;
+        compress::compress(
+            &snapshot_dir
+                .join(NOITA_SAVE_DIRECTORY)
+                .join(NOITA_WORLD_DIRECTORY),
+        )
+    })?;
+    let restore_secs = timed(|| {
+        copy_with(
+            &chain,
+            &snapshot_dir.join(NOITA_SAVE_DIRECTORY),
+            &restore_dir,
+        )?;
+        compress::decompress(
+            &restore_dir
+                .join(NOITA_SAVE_DIRECTORY)
+                .join(NOITA_WORLD_DIRECTORY),
+        )
+    })?;
+    say_bench("compressed", content.dir_size, snapshot_secs, restore_secs);
+
+    let (snapshot_dir, restore_dir) = reset_bench_dir(&bench_dir, false)?;
+    let snapshot_secs = timed(|| store_copy(&noita_save_dir, &snapshot_dir).map(|_| ()))?;
+    let restore_secs = timed(|| {
+        copy_with(
+            &chain,
+            &snapshot_dir.join(NOITA_SAVE_DIRECTORY),
+            &restore_dir,
+        )
+        .map(|_| ())
+    })?;
+    say_bench("stored", content.dir_size, snapshot_secs, restore_secs);
+
+    // Files only the benchmark put in the store are not kept around
+    set_save_readonly(&bench_dir, true, false)?;
+    fs::remove_dir_all(&bench_dir)?;
+    collect_store_garbage()?;
+    Ok(())
+}
+
+// Empties the benchmark directory, returning where to snapshot and restore to
+fn reset_bench_dir(bench_dir: &Path, stored: bool) -> Result<(PathBuf, PathBuf), CliError> {
+    if bench_dir.exists() {
+        set_save_readonly(bench_dir, stored, false)?;
+        fs::remove_dir_all(bench_dir)?;
+    }
+    let snapshot_dir = bench_dir.join("snapshot");
+    let restore_dir = bench_dir.join("restore");
+    fs::create_dir_all(&snapshot_dir)?;
+    fs::create_dir_all(&restore_dir)?;
+    Ok((snapshot_dir, restore_dir))
+}
+
+fn timed<F>(operation: F) -> Result<f64, CliError>
+where
+    F: FnOnce() -> Result<(), CliError>,
+{
+    let start = Instant::now();
+    operation()?;
+    Ok(start.elapsed().as_secs_f64())
+}
+
+fn say_bench(name: &str, bytes: u64, snapshot_secs: f64, restore_secs: f64) {
+    say!(
+        "{:<10} snapshot {:>8.2}s ({}/s)   restore {:>8.2}s ({}/s)",
+        name,
+        snapshot_secs,
+        human_size((bytes as f64 / snapshot_secs) as u64),
+        restore_secs,
+        human_size((bytes as f64 / restore_secs) as u64)
+    );
+}
+
+fn list_saves(config: &Config, options: &ListOptions) -> Result<(), CliError> {
+    let mut gamedb = if options.size || options.sort == SortOrder::Size {
+        load_gamedb_with_sizes()?
+    } else {
+        load_gamedb()?
+    };
+    if options.details || options.min_orbs.is_some() {
+        index_saves(&mut gamedb, false)?;
+    }
+    gamedb.saves.retain(|save| options.includes(save));
+    options.sort(&mut gamedb.saves);
+
+    if output::is_json() {
+        output::emit(&saves_output(&gamedb, &gamedb.saves, None));
+        return Ok(());
+    }
+    if gamedb.saves.is_empty() {
+        say!("No saves found");
+        return Ok(());
+    }
+
+    print_saves(config, &gamedb, &gamedb.saves, options, None);
+    if options.size {
+        show!("Total {}", total_size(&gamedb.saves));
+        if !gamedb.trash.is_empty() {
+            show!(
+                "Removed saves take up another {}",
+                total_size(&gamedb.trash)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn save_info(save_name: &str, show_inventory: bool) -> Result<(), CliError> {
+    let mut gamedb = load_gamedb_with_sizes()?;
+    index_saves(&mut gamedb, false)?;
+    let save = match gamedb
+        .saves
+        .iter()
+        .chain(gamedb.trash.iter())
+        .find(|save| save.name == save_name)
+    {
+        Some(save) => save,
+        None => {
+            show!(
+                "{}",
+                not_found(save_name, gamedb.saves.iter().chain(gamedb.trash.iter()))
+            );
+            return Ok(());
+        }
+    };
+    if output::is_json() {
+        output::emit(&saves_output(&gamedb, std::slice::from_ref(save), None)[0]);
+        return Ok(());
+    }
+    for line in save_details(&gamedb, save) {
+        show!("{}", line);
+    }
+
+    if !show_inventory {
+        return Ok(());
+    }
+    let inventory = match save
+        .index
+        .as_ref()
+        .and_then(|index| index.inventory.as_ref())
+    {
+        Some(inventory) => inventory,
+        None => {
+            say!("No inventory found");
+            return Ok(());
+        }
+    };
+    show!("Wands");
+    for (index, wand) in inventory.wands.iter().enumerate() {
+        let capacity = match wand.capacity {
+            Some(capacity) => format!(", capacity {}", capacity),
+            None => String::new(),
+        };
+        show!(
+            "  {}. {}{}: {}",
+            index + 1,
+            wand.name,
+            capacity,
+            list_or_none(&wand.spells)
+        );
+    }
+    show!("Spells    {}", list_or_none(&inventory.spells));
+    show!("Perks     {}", list_or_none(&inventory.perks));
+    Ok(())
+}
+
+// What `info` shows about a save, one line at a time
+fn save_details(gamedb: &GameDB, save: &Save) -> Vec<String> {
+    let index = save.index.as_ref();
+    let mut lines = vec![format!("[{}]", save.name)];
+    match &save.created_by {
+        Some(version) => lines.push(format!(
+            "Created   {} by nauttaja {}",
+            save.timestamp, version
+        )),
+        None => lines.push(format!("Created   {}", save.timestamp)),
+    }
+    if let Some(noita_root_dir) = &save.noita_root_dir {
+        lines.push(format!("Saved in  [{}]", noita_root_dir));
+    }
+    if let Some(removed) = &save.removed {
+        lines.push(format!("Removed   {}", removed));
+    }
+    if let Some(note) = &save.note {
+        lines.push(format!("Note      {}", note));
+    }
+    if gamedb
+        .loaded
+        .as_ref()
+        .is_some_and(|loaded| loaded.directory == save.directory)
+    {
+        lines.push(
+            "Loaded    yes, the current save was loaded from, or saved as, this save".to_string(),
+        );
+    }
+    if let (Some(loads), Some(last_loaded)) = (save.loads, &save.last_loaded) {
+        lines.push(format!(
+            "Loads     {}, last {}",
+            loads,
+            age(last_loaded).unwrap_or_else(|| last_loaded.clone())
+        ));
+    }
+    let size = size_summary(save);
+    if !size.is_empty() {
+        lines.push(format!("Size      {}", size.trim_start_matches(", ")));
+    }
+    match save.health {
+        Some(health) => lines.push(format!("Health    {}", health.describe())),
+        None => lines.push("Health    not verified yet".to_string()),
+    }
+    match index.and_then(|index| index.progress.as_ref()) {
+        Some(progress) => {
+            lines.push(format!("Run       {}", describe_progress(progress)));
+            if !progress.bosses.is_empty() {
+                lines.push(format!("Bosses    {}", progress.bosses.join(", ")));
+            }
+        }
+        None => lines.push("Run       no run in progress".to_string()),
+    }
+    if let Some(stats) = index.and_then(|index| index.stats.as_ref()) {
+        if let Some(seed) = &stats.seed {
+            lines.push(format!("Seed      {}", seed));
+        }
+        if let Some(playtime) = stats.playtime_secs {
+            lines.push(format!("Playtime  {}", describe_playtime(playtime)));
+        }
+        if let Some(gold) = stats.gold {
+            lines.push(format!("Gold      {}", gold));
+        }
+        if let Some(kills) = stats.kills {
+            lines.push(format!("Kills     {}", kills));
+        }
+    }
+    lines
+}
+
+fn search_saves(items: &[&str]) -> Result<(), CliError> {
+    let mut gamedb = load_gamedb_indexed()?;
+    gamedb.saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    gamedb.trash.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+    let mut found = 0;
+    let mut results = Vec::new();
+    for save in gamedb.saves.iter().chain(gamedb.trash.iter()) {
+        let inventory = match save
+            .index
+            .as_ref()
+            .and_then(|index| index.inventory.as_ref())
+        {
+            Some(inventory) => inventory,
+            None => continue,
+        };
+        let mut places = Vec::new();
+        let all_found = items.iter().all(|item| {
+            let places_before = places.len();
+            for (index, wand) in inventory.wands.iter().enumerate() {
+                if wand
+                    .spells
+                    .iter()
+                    .any(|spell| spell.eq_ignore_ascii_case(item))
+                {
+                    places.push(format!("{} on wand {}", item.to_uppercase(), index + 1));
+                }
+            }
+            if inventory
+                .spells
+                .iter()
+                .any(|spell| spell.eq_ignore_ascii_case(item))
+            {
+                places.push(format!("{} in the inventory", item.to_uppercase()));
+            }
+            if inventory
+                .perks
+                .iter()
+                .any(|perk| perk.eq_ignore_ascii_case(item))
+            {
+                places.push(format!("{} as a perk", item.to_uppercase()));
+            }
+            places.len() > places_before
+        });
+        if !all_found {
+            continue;
+        }
+        found += 1;
+        if output::is_json() {
+            results.push(json!({
+                "name": save.name,
+                "removed": save.removed.is_some(),
+                "places": places,
+            }));
+            continue;
+        }
+        let removed = if save.removed.is_some() {
+            ", removed"
+        } else {
+            ""
+        };
+        show!(
+            "{} - {} ({}{})",
+            save.timestamp,
+            save.name,
+            places.join(", "),
+            removed
+        );
+    }
+    if output::is_json() {
+        output::emit(&results);
+        return Ok(());
+    }
+    if found == 0 {
+        say!("No saves found with {}", items.join(" and "));
+    }
+    Ok(())
+}
+
+fn diff_saves(a: &str, b: &str, stats: bool) -> Result<(), CliError> {
+    let gamedb = if stats {
+        load_gamedb_indexed()?
+    } else {
+        load_gamedb()?
+    };
+    let find = |name: &str| {
+        gamedb
+            .saves
+            .iter()
+            .chain(gamedb.trash.iter())
+            .find(|save| save.name == name)
+    };
+    let (a, b) = match (find(a), find(b)) {
+        (Some(a), Some(b)) => (a, b),
+        (None, _) => {
+            show!(
+                "{}",
+                not_found(a, gamedb.saves.iter().chain(gamedb.trash.iter()))
+            );
+            return Ok(());
+        }
+        (_, None) => {
+            show!(
+                "{}",
+                not_found(b, gamedb.saves.iter().chain(gamedb.trash.iter()))
+            );
+            return Ok(());
+        }
+    };
+    if stats {
+        diff_stats(a, b);
+        return Ok(());
+    }
+
+    let (expected, actual) = match (
+        manifest::read(&save_path(a)?)?,
+        manifest::read(&save_path(b)?)?,
+    ) {
+        (Some(expected), Some(actual)) => (expected, actual),
+        (None, _) => {
+            say!("[{}] has no manifest, run nauttaja scrub first", a.name);
+            return Ok(());
+        }
+        (_, None) => {
+            say!("[{}] has no manifest, run nauttaja scrub first", b.name);
+            return Ok(());
+        }
+    };
+    let verification = manifest::compare(&expected, &actual);
+    if verification.is_ok() {
+        show!("[{}] and [{}] are identical", a.name, b.name);
+        return Ok(());
+    }
+    for path in &verification.unexpected {
+        show!("+ {}", path);
+    }
+    for path in &verification.missing {
+        show!("- {}", path);
+    }
+    for path in &verification.changed {
+        show!("~ {}", path);
+    }
+    show!(
+        "{} added, {} removed, {} changed",
+        verification.unexpected.len(),
+        verification.missing.len(),
+        verification.changed.len()
+    );
+    Ok(())
+}
+
+fn diff_stats(a: &Save, b: &Save) {
+    let (before, after) = match (a.index.as_ref(), b.index.as_ref()) {
+        (Some(before), Some(after)) => (before, after),
+        _ => {
+            say!("Failed to read the saves");
+            return;
+        }
+    };
+    let seed = |index: &noita::SaveIndex| index.stats.as_ref().and_then(|stats| stats.seed.clone());
+    if seed(before) != seed(after) {
+        show!(
+            "Warning: [{}] and [{}] are not from the same run",
+            a.name,
+            b.name
+        );
+    }
+    show!("[{}] -> [{}]", a.name, b.name);
+
+    let (progress_before, progress_after) = (before.progress.as_ref(), after.progress.as_ref());
+    let hp = |progress: Option<&noita::RunProgress>| match progress {
+        Some(noita::RunProgress {
+            hp: Some(hp),
+            max_hp: Some(max_hp),
+            ..
+        }) => format!("{:.0}/{:.0}", hp, max_hp),
+        _ => "unknown".to_string(),
+    };
+    show!(
+        "HP        {} -> {}",
+        hp(progress_before),
+        hp(progress_after)
+    );
+    let describe = |progress: Option<&noita::RunProgress>| {
+        progress
+            .map(describe_progress)
+            .unwrap_or_else(|| "no run in progress".to_string())
+    };
+    show!(
+        "Run       {} -> {}",
+        describe(progress_before),
+        describe(progress_after)
+    );
+
+    let (stats_before, stats_after) = (before.stats.as_ref(), after.stats.as_ref());
+    show!(
+        "Gold      {}",
+        with_delta(
+            stats_after.and_then(|stats| stats.gold),
+            stats_before.and_then(|stats| stats.gold),
+            |gold| gold.to_string()
+        )
+    );
+    show!(
+        "Kills     {}",
+        with_delta(
+            stats_after.and_then(|stats| stats.kills),
+            stats_before.and_then(|stats| stats.kills),
+            |kills| kills.to_string()
+        )
+    );
+
+    let empty = noita::Inventory {
+        wands: Vec::new(),
+        spells: Vec::new(),
+        perks: Vec::new(),
+    };
+    let inventory_before = before.inventory.as_ref().unwrap_or(&empty);
+    let inventory_after = after.inventory.as_ref().unwrap_or(&empty);
+    let wand_names = |inventory: &noita::Inventory| -> Vec<String> {
+        inventory
+            .wands
+            .iter()
+            .map(|wand| wand.name.clone())
+            .collect()
+    };
+    let all_spells = |inventory: &noita::Inventory| -> Vec<String> {
+        inventory
+            .wands
+            .iter()
+            .flat_map(|wand| wand.spells.iter())
+            .chain(inventory.spells.iter())
+            .cloned()
+            .collect()
+    };
+    show!(
+        "Perks     {}",
+        describe_changes(&inventory_before.perks, &inventory_after.perks)
+    );
+    show!(
+        "Wands     {}",
+        describe_changes(&wand_names(inventory_before), &wand_names(inventory_after))
+    );
+    show!(
+        "Spells    {}",
+        describe_changes(&all_spells(inventory_before), &all_spells(inventory_after))
+    );
+}
+
+// Lists what was gained and lost, counting duplicates
+fn describe_changes(before: &[String], after: &[String]) -> String {
+    let mut lost = before.to_vec();
+    let mut gained = Vec::new();
+    for item in after {
+        match lost.iter().position(|other| other == item) {
+            Some(position) => {
+                lost.remove(position);
+            }
+            None => gained.push(format!("+{}", item)),
+        }
+    }
+    let changes: Vec<String> = gained
+        .into_iter()
+        .chain(lost.into_iter().map(|item| format!("-{}", item)))
+        .collect();
+    if changes.is_empty() {
+        "unchanged".to_string()
+    } else {
+        changes.join(", ")
+    }
+}
+
+fn describe_playtime(playtime_secs: f64) -> String {
+    let minutes = (playtime_secs / 60.0) as u64;
+    format!("{}h {:0>2}m", minutes / 60, minutes % 60)
+}
+
+// Describes the change of a statistic since the previous save of the run
+fn with_delta<T>(current: Option<T>, previous: Option<T>, describe: fn(T) -> String) -> String
+where
+    T: Copy + PartialOrd + std::ops::Sub<Output = T>,
+{
+    match (current, previous) {
+        (Some(current), Some(previous)) if current >= previous => {
+            format!("{} (+{})", describe(current), describe(current - previous))
+        }
+        (Some(current), Some(previous)) => {
+            format!("{} (-{})", describe(current), describe(previous - current))
+        }
+        (Some(current), None) => describe(current),
+        (None, _) => "unknown".to_string(),
+    }
+}
+
+// Writes every save of a run, in order, as a Markdown document
+fn run_journal(run: &str) -> Result<(), CliError> {
+    let gamedb = load_gamedb_indexed()?;
+    let seed_of = |save: &Save| {
+        save.index
+            .as_ref()
+            .and_then(|index| index.stats.as_ref())
+            .and_then(|stats| stats.seed.clone())
+    };
+    let seed = match gamedb.saves.iter().find(|save| save.name == run) {
+        Some(save) => match seed_of(save) {
+            Some(seed) => seed,
+            None => {
+                say!("The seed of [{}] is unknown", run);
+                return Ok(());
+            }
+        },
+        None => run.to_string(),
+    };
+    let mut saves: Vec<&Save> = gamedb
+        .saves
+        .iter()
+        .filter(|save| seed_of(save).as_deref() == Some(seed.as_str()))
+        .collect();
+    if saves.is_empty() {
+        say!("Failed to find a save or run with seed [{}]", run);
+        return Ok(());
+    }
+    saves.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+    show!("# Run {}", seed);
+    let mut previous: Option<&noita::SaveIndex> = None;
+    for save in saves {
+        let index = save.index.as_ref().expect("Saves of a run are indexed");
+        show!();
+        show!("## {} - {}", save.timestamp, save.name);
+        show!();
+        if let Some(note) = &save.note {
+            show!("> {}", note);
+            show!();
+        }
+        if let Some(progress) = &index.progress {
+            show!("- Run: {}", describe_progress(progress));
+            let killed: Vec<&str> = progress
+                .bosses
+                .iter()
+                .filter(|boss| {
+                    !previous
+                        .and_then(|previous| previous.progress.as_ref())
+                        .is_some_and(|previous| previous.bosses.contains(boss))
+                })
+                .map(|boss| boss.as_str())
+                .collect();
+            if !killed.is_empty() {
+                show!("- Killed: {}", killed.join(", "));
+            }
+        }
+        let stats = index.stats.as_ref().expect("Saves of a run have stats");
+        let before = previous.and_then(|previous| previous.stats.as_ref());
+        show!(
+            "- Playtime: {}",
+            with_delta(
+                stats.playtime_secs,
+                before.and_then(|before| before.playtime_secs),
+                describe_playtime
+            )
+        );
+        show!(
+            "- Gold: {}",
+            with_delta(stats.gold, before.and_then(|before| before.gold), |gold| {
+                gold.to_string()
+            })
+        );
+        show!(
+            "- Kills: {}",
+            with_delta(
+                stats.kills,
+                before.and_then(|before| before.kills),
+                |kills| kills.to_string()
+            )
+        );
+        if let Some(inventory) = &index.inventory {
+            let perks: Vec<&str> = inventory
+                .perks
+                .iter()
+                .filter(|perk| {
+                    !previous
+                        .and_then(|previous| previous.inventory.as_ref())
+                        .is_some_and(|previous| previous.perks.contains(perk))
+                })
+                .map(|perk| perk.as_str())
+                .collect();
+            if !perks.is_empty() {
+                show!("- New perks: {}", perks.join(", "));
+            }
+        }
+        previous = Some(index);
+    }
+
+    let sessions: Vec<&Session> = gamedb
+        .sessions
+        .iter()
+        .filter(|session| session.seed.as_deref() == Some(seed.as_str()))
+        .collect();
+    if !sessions.is_empty() {
+        show!();
+        show!("## Sessions");
+        show!();
+        for session in sessions {
+            let date = session.started.split(' ').next().unwrap_or_default();
+            show!("- {} {}", date, describe_session_span(session));
+        }
+    }
+    Ok(())
+}
+
+// Every session seen by watch, oldest first, making up a diary of play
+fn session_journal() -> Result<(), CliError> {
+    let gamedb = load_gamedb()?;
+    if gamedb.sessions.is_empty() {
+        say!("No sessions recorded yet, they are recorded by nauttaja watch when Noita exits");
+        return Ok(());
+    }
+    show!("# Sessions");
+    let mut day = "";
+    for session in &gamedb.sessions {
+        let date = session.started.split(' ').next().unwrap_or_default();
+        if date != day {
+            day = date;
+            show!();
+            show!("## {}", day);
+            show!();
+        }
+        show!("- {}", describe_session_span(session));
+    }
+    Ok(())
+}
+
+// Such as "18:02 to 19:10: played 1h 05m of run 123456789, ..."
+fn describe_session_span(session: &Session) -> String {
+    let time = |timestamp: &str| match parse_timestamp(timestamp) {
+        Some(time) => time.format("%H:%M").to_string(),
+        None => timestamp.to_string(),
+    };
+    format!(
+        "{} to {}: {}",
+        time(&session.started),
+        time(&session.ended),
+        describe_session(session)
+    )
+}
+
+fn list_or_none(items: &[String]) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+fn status(config: &Config) -> Result<(), CliError> {
+    let gamedb = load_gamedb_with_sizes()?;
+    let trash_size: u64 = gamedb.trash.iter().filter_map(|save| save.size).sum();
+    let noita_save_dir = noita_save_dir(config);
+    if output::is_json() {
+        let current = gamedb.loaded.as_ref().and_then(|loaded| {
+            gamedb
+                .saves
+                .iter()
+                .chain(gamedb.trash.iter())
+                .find(|save| save.directory == loaded.directory)
+        });
+        let changed = match &gamedb.loaded {
+            Some(loaded) if noita_save_dir.exists() => {
+                Some(manifest::generate(&noita_save_dir)?.digest("") != loaded.digest)
             }
-        } else {
-            let deleted = gamedb.trash.remove(index.unwrap());
-            dir_to_delete = Some(deleted.directory);
+            _ => None,
+        };
+        output::emit(&json!({
+            "noita_root_dir": config.noita_root_dir,
+            "saves": gamedb.saves.len(),
+            "removed": gamedb.trash.len(),
+            "trash_size": trash_size,
+            "trash_quota": config.trash_quota_mib.map(|quota| quota * 1024 * 1024),
+            "current_save": noita_save_dir.exists(),
+            "loaded_from": current.map(|save| &save.name),
+            "loaded_at": gamedb.loaded.as_ref().map(|loaded| &loaded.timestamp),
+            "changed": changed,
+            "low_disk_space": low_disk_space(config)?
+                .iter()
+                .map(|low| json!({
+                    "volume": low.volume.name,
+                    "free": low.volume.free,
+                    "holds": low.holds,
+                }))
+                .collect::<Vec<_>>(),
+        }));
+        return Ok(());
+    }
+    show!("Noita directory [{}]", config.noita_root_dir);
+    show!(
+        "{} saves, {} removed saves taking up {}{}",
+        gamedb.saves.len(),
+        gamedb.trash.len(),
+        human_size(trash_size),
+        match config.trash_quota_mib {
+            Some(quota) => format!(" of {}", human_size(quota * 1024 * 1024)),
+            None => String::new(),
         }
-        gamedb
-    })?;
+    );
+    for low in low_disk_space(config)? {
+        show!("{}", low);
+    }
 
-    if dir_to_delete.is_some() {
-        let dir = dir_to_delete.unwrap();
-        let work_dir = nauttaja_dir()?;
-        let save_dir = work_dir.join(NAUTTAJA_SAVES_DIRECTORY).join(dir);
+    if !noita_save_dir.exists() {
+        show!("No current save found");
+        return Ok(());
+    }
+    let loaded = match &gamedb.loaded {
+        Some(loaded) => loaded,
+        None => {
+            show!("The current save was not loaded or saved by nauttaja");
+            return Ok(());
+        }
+    };
 
-        fs::remove_dir_all(save_dir)?;
-        println!("Deleted save successfully");
+    let since = age(&loaded.timestamp).unwrap_or_else(|| loaded.timestamp.clone());
+    if let Some(save) = gamedb
+        .saves
+        .iter()
+        .find(|save| save.directory == loaded.directory)
+    {
+        show!("Current save [{}], loaded {}", save.name, since);
+    } else if let Some(save) = gamedb
+        .trash
+        .iter()
+        .find(|save| save.directory == loaded.directory)
+    {
+        show!(
+            "Current save [{}], loaded {}, now in the trash",
+            save.name,
+            since
+        );
+    } else {
+        show!(
+            "Current save was loaded {}, from a save which has since been deleted",
+            since
+        );
     }
 
+    if manifest::generate(&noita_save_dir)?.digest("") == loaded.digest {
+        show!("The current save is unchanged since then");
+    } else {
+        show!("The current save has changed since then");
+    }
     Ok(())
 }
 
-fn remove_save(save_name: &str) -> Result<(), CliError> {
-    println!("Removing save with name [{}]", save_name);
-    update_gamedb(|mut gamedb: GameDB| {
-        let index = gamedb.saves.iter().position(|item| item.name == save_name);
-        if index.is_none() {
-            println!("Failed to find [{}]", save_name);
-        } else {
-            let index = index.unwrap();
-            gamedb.trash.push(gamedb.saves.remove(index));
-        }
-        gamedb
-    })?;
-    println!("Save with name [{}] removed", save_name);
-    Ok(())
+// A save as written with --json
+#[derive(Serialize)]
+struct SaveOutput<'a> {
+    #[serde(flatten)]
+    save: &'a Save,
+    loaded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purges_in_days: Option<i64>,
 }
 
-fn restore_save(save_name: &str) -> Result<(), CliError> {
-    println!("Restoring save with name [{}]", save_name);
-    update_gamedb(|mut gamedb: GameDB| {
-        let index = gamedb.trash.iter().position(|item| item.name == save_name);
-        if index.is_none() {
-            println!("Failed to find [{}]", save_name);
-        } else {
-            let index = index.unwrap();
-            gamedb.saves.push(gamedb.trash.remove(index));
-        }
-        gamedb
-    })?;
-    println!("Save with name [{}] restored", save_name);
-    Ok(())
+fn saves_output<'a>(
+    gamedb: &GameDB,
+    saves: &'a [Save],
+    trash_retention_days: Option<u32>,
+) -> Vec<SaveOutput<'a>> {
+    saves
+        .iter()
+        .map(|save| SaveOutput {
+            save,
+            loaded: gamedb
+                .loaded
+                .as_ref()
+                .is_some_and(|loaded| loaded.directory == save.directory),
+            purges_in_days: purges_in_days(save, trash_retention_days),
+        })
+        .collect()
 }
 
-fn import_save(directory: &str, save_name: &str) -> Result<(), CliError> {
-    println!(
-        "Importing directory [{}] as a new save, named [{}]",
-        directory, save_name
-    );
-    save_dir_as_save(directory, save_name)?;
-    println!(
-        "Successfully imported directory as a save with name [{}]",
-        directory
-    );
-    Ok(())
+// Days left until a removed save is deleted from the trash, rounded up, so a
+// save removed just now with a retention of 30 days purges in 30 days
+fn purges_in_days(save: &Save, trash_retention_days: Option<u32>) -> Option<i64> {
+    let days = trash_retention_days.filter(|days| *days > 0)?;
+    let removed = parse_timestamp(save.removed.as_deref()?)?;
+    let left = removed + chrono::Duration::days(days.into()) - Local::now().naive_local();
+    Some((left.num_hours().max(0) + 23) / 24)
 }
 
-fn save_game(config: &Config, save_name: &str) -> Result<(), CliError> {
-    println!("Saving game with name [{}]", save_name);
-    save_dir_as_save(noita_save_dir(config), save_name)?;
-    println!("Successfully saved game with name [{}]", save_name);
-    Ok(())
+#[derive(Default)]
+struct ListOptions {
+    size: bool,
+    details: bool,
+    min_orbs: Option<usize>,
+    pattern: Option<Regex>,
+    sort: SortOrder,
+    reverse: bool,
 }
 
-fn save_dir_as_save<T>(directory: T, save_name: &str) -> Result<(), CliError>
-where
-    T: AsRef<Path>,
-{
-    let gamedb = load_gamedb()?;
+#[derive(Default, PartialEq)]
+enum SortOrder {
+    #[default]
+    Date,
+    Name,
+    Size,
+    LastLoaded,
+}
 
-    if gamedb.saves.iter().any(|item| item.name == save_name) {
-        println!("[{}] already exists", save_name);
-        return Ok(());
-    }
-    if gamedb.trash.iter().any(|item| item.name == save_name) {
-        println!("[{}] already exists, currently in the trash", save_name);
-        return Ok(());
+impl ListOptions {
+    fn sort(&self, saves: &mut [Save]) {
+        match self.sort {
+            SortOrder::Date => saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)),
+            SortOrder::Name => saves.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortOrder::Size => saves.sort_by_key(|save| std::cmp::Reverse(save.size)),
+            // Saves which were never loaded come last, newest first
+            SortOrder::LastLoaded => saves.sort_by(|a, b| {
+                (&b.last_loaded, &b.timestamp).cmp(&(&a.last_loaded, &a.timestamp))
+            }),
+        }
+        if self.reverse {
+            saves.reverse();
+        }
     }
 
-    let work_dir = nauttaja_dir()?;
-    let save_dir_name = uuid();
-    let save_dir = work_dir
-        .join(NAUTTAJA_SAVES_DIRECTORY)
-        .join(save_dir_name.clone());
-
-    fs::create_dir_all(save_dir.clone())?;
-
-    copy_dir(directory, save_dir)?;
+    fn includes(&self, save: &Save) -> bool {
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(&save.name) {
+                return false;
+            }
+        }
+        match self.min_orbs {
+            Some(min_orbs) => save
+                .index
+                .as_ref()
+                .and_then(|index| index.progress.as_ref())
+                .and_then(|progress| progress.orbs)
+                .is_some_and(|orbs| orbs >= min_orbs),
+            None => true,
+        }
+    }
+}
 
-    update_gamedb(|mut gamedb: GameDB| {
-        let save = Save {
-            name: save_name.to_string(),
-            directory: save_dir_name.clone(),
-            timestamp: timestamp(),
-        };
-        gamedb.saves.push(save);
-        gamedb
-    })?;
+fn print_details(save: &Save) {
+    match save
+        .index
+        .as_ref()
+        .and_then(|index| index.progress.as_ref())
+    {
+        Some(progress) => {
+            let bosses: String = progress
+                .bosses
+                .iter()
+                .map(|boss| format!("  ☠ {}", boss))
+                .collect();
+            show!("    {}{}", describe_progress(progress), bosses)
+        }
+        None => show!("    no run in progress"),
+    }
+}
 
-    Ok(())
+fn describe_progress(progress: &noita::RunProgress) -> String {
+    let orbs = match progress.orbs {
+        Some(1) => ", 1 orb".to_string(),
+        Some(orbs) => format!(", {} orbs", orbs),
+        None => String::new(),
+    };
+    format!("depth {:.0}, {}{}", progress.depth, progress.biome, orbs)
 }
 
-fn load_save(config: &Config, save_name: &str) -> Result<(), CliError> {
-    println!("Loading save with name [{}]", save_name);
+// One row per save, with the columns lined up and colored when writing to a
+// terminal. Notes and details go below the row of their save
+fn print_saves(
+    config: &Config,
+    gamedb: &GameDB,
+    saves: &[Save],
+    options: &ListOptions,
+    trash_retention_days: Option<u32>,
+) {
+    let loaded = gamedb
+        .loaded
+        .as_ref()
+        .map(|loaded| loaded.directory.as_str());
+    let sizes: Vec<String> = saves
+        .iter()
+        .map(|save| match (save.size, save.files) {
+            (Some(size), Some(files)) if options.size => {
+                format!("{} in {} files", human_size(size), files)
+            }
+            _ => String::new(),
+        })
+        .collect();
+    let names: Vec<String> = saves
+        .iter()
+        .map(|save| {
+            truncate(
+                &save.name,
+                config.list_name_width,
+                config.list_name_ellipsis,
+            )
+        })
+        .collect();
+    let date_width = saves
+        .iter()
+        .map(|save| save.timestamp.width())
+        .max()
+        .unwrap_or_default();
+    let name_width = names
+        .iter()
+        .map(|name| name.width())
+        .chain(Some("Name".len()))
+        .max()
+        .unwrap_or_default();
+    let size_width = sizes
+        .iter()
+        .map(|size| size.width())
+        .chain(Some("Size".len()))
+        .max()
+        .unwrap_or_default();
 
-    let work_dir = nauttaja_dir()?;
-    let backup_dir = work_dir.join(NAUTTAJA_LAST_REPLACED_DIRECTORY);
-    let gamedb = load_gamedb()?;
-    let save = gamedb.saves.iter().find(|item| item.name == save_name);
-    if save.is_none() {
-        println!("Failed to find save with name [{}]", save_name);
-        return Ok(());
+    let mut header = format!("{:<date_width$}  {:<name_width$}", "Date", "Name");
+    if options.size {
+        header.push_str(&format!("  {:>size_width$}", "Size"));
     }
-    let save = save.unwrap();
-    let save_dir = work_dir
-        .join(NAUTTAJA_SAVES_DIRECTORY)
-        .join(save.directory.clone());
+    header.push_str("  Tags");
+    show!("{}", output::styled(header, |text| text.bold()));
 
-    if !save_dir.exists() {
-        println!("Failed to find save with name [{}]", save_name);
-        return Ok(());
+    for ((save, name), size) in saves.iter().zip(&names).zip(&sizes) {
+        let mut tags = Vec::new();
+        if let Some(age) = save.removed.as_deref().and_then(age) {
+            tags.push(output::styled(format!("removed {}", age), |text| {
+                text.dim()
+            }));
+        }
+        if let Some(days) = purges_in_days(save, trash_retention_days) {
+            let purge = match days {
+                0 => "purges next time nauttaja runs".to_string(),
+                1 => "purges in 1 day".to_string(),
+                days => format!("purges in {} days", days),
+            };
+            tags.push(output::styled(purge, |text| text.dim()));
+        }
+        if let Some(slot) = save.slot {
+            tags.push(output::styled(format!("slot {}", slot), |text| text.blue()));
+        }
+        if save.autosave {
+            tags.push(output::styled("autosave".to_string(), |text| text.yellow()));
+        }
+        if Some(save.directory.as_str()) == loaded {
+            tags.push(output::styled("loaded".to_string(), |text| text.green()));
+        }
+        if let Some(health) = save.health.filter(Health::is_unhealthy) {
+            tags.push(output::styled(
+                format!("⚠ {}", health.describe()),
+                |text| text.red(),
+            ));
+        }
+
+        let mut row = format!(
+            "{}  {}",
+            output::styled(format!("{:<date_width$}", save.timestamp), |text| text
+                .dim()),
+            output::styled(name.clone(), |text| text.bold())
+        );
+        // Padded outside of the styling, so trailing spaces can be trimmed,
+        // by the columns the name takes up, since wide characters take two
+        // and combining marks none
+        row.push_str(&" ".repeat(name_width - name.width()));
+        if options.size {
+            row.push_str(&format!(
+                "  {}",
+                output::styled(format!("{:>size_width$}", size), |text| text.cyan())
+            ));
+        }
+        if !tags.is_empty() {
+            row.push_str(&format!("  {}", tags.join(", ")));
+        }
+        show!("{}", row.trim_end());
+        if let Some(note) = &save.note {
+            let note = truncate(note, config.list_note_width, config.list_note_ellipsis);
+            show!("    {}", output::styled(note, |text| text.italic()));
+        }
+        if options.details {
+            print_details(save);
+        }
     }
+}
 
-    if backup_dir.exists() {
-        fs::remove_dir_all(backup_dir.clone())?;
+// Shortens text to the given number of terminal columns, 0 being unlimited,
+// marking where anything was left out with an ellipsis
+fn truncate(text: &str, columns: usize, ellipsis: Ellipsis) -> String {
+    if columns == 0 || text.width() <= columns {
+        return text.to_string();
+    }
+    let room = columns - 1;
+    match ellipsis {
+        Ellipsis::End => format!("{}…", leading(text, room)),
+        Ellipsis::Middle => format!(
+            "{}…{}",
+            leading(text, room - room / 2),
+            trailing(text, room / 2)
+        ),
+        Ellipsis::Start => format!("…{}", trailing(text, room)),
     }
-    fs::create_dir(backup_dir.clone())?;
+}
 
-    println!("Creating emergency backup...");
-    copy_dir(noita_save_dir(config), backup_dir)?;
+// The start of text taking up at most the given number of terminal columns
+fn leading(text: &str, columns: usize) -> String {
+    let mut width = 0;
+    text.chars()
+        .take_while(|character| {
+            width += character.width().unwrap_or(0);
+            width <= columns
+        })
+        .collect()
+}
 
-    println!("Loading [{}]...", save_name);
-    fs::remove_dir_all(noita_save_dir(config))?;
+// The end of text taking up at most the given number of terminal columns,
+// without combining marks whose character was left out
+fn trailing(text: &str, columns: usize) -> String {
+    let mut width = 0;
+    let mut characters: Vec<char> = text
+        .chars()
+        .rev()
+        .take_while(|character| {
+            width += character.width().unwrap_or(0);
+            width <= columns
+        })
+        .collect();
+    while characters
+        .last()
+        .is_some_and(|character| character.width() == Some(0))
+    {
+        characters.pop();
+    }
+    characters.into_iter().rev().collect()
+}
 
-    copy_dir(
-        save_dir.join(NOITA_SAVE_DIRECTORY),
-        config.noita_root_dir.clone(),
-    )?;
+fn size_summary(save: &Save) -> String {
+    match (save.size, save.files) {
+        (Some(size), Some(files)) => format!(", {} in {} files", human_size(size), files),
+        _ => String::new(),
+    }
+}
 
-    println!("Save [{}] successfully loaded!", save_name);
-    Ok(())
+fn total_size(saves: &[Save]) -> String {
+    let size: u64 = saves.iter().filter_map(|save| save.size).sum();
+    match saves.len() {
+        1 => format!("{} in 1 save", human_size(size)),
+        count => format!("{} in {} saves", human_size(size), count),
+    }
 }
 
-fn list_saves() -> Result<(), CliError> {
+// Sizes are cached in the gamedb when a save is created, saves from older
+// versions have theirs computed once and stored the first time they are needed
+fn load_gamedb_with_sizes() -> Result<GameDB, CliError> {
     let mut gamedb = load_gamedb()?;
-
-    if gamedb.saves.is_empty() {
-        println!("No saves found");
-        return Ok(());
+    let mut computed = Vec::new();
+    for save in gamedb.saves.iter_mut().chain(gamedb.trash.iter_mut()) {
+        if save.size.is_some() && save.files.is_some() {
+            continue;
+        }
+        let save_dir = save_path(save)?;
+        if !save_dir.exists() {
+            continue;
+        }
+        // Like at snapshot time, only files other than the manifest are counted
+        let manifest_file = save_dir.join(manifest::MANIFEST_FILE);
+        let (mut size, mut files) = (0, 0);
+        for file in dir::get_dir_content(&save_dir)?.files {
+            if Path::new(&file) != manifest_file {
+                size += fs::metadata(&file)?.len();
+                files += 1;
+            }
+        }
+        save.size = Some(size);
+        save.files = Some(files);
+        computed.push(save.clone());
     }
+    if !computed.is_empty() {
+        update_gamedb(|mut gamedb: GameDB| {
+            for save in gamedb.saves.iter_mut().chain(gamedb.trash.iter_mut()) {
+                if let Some(computed) = computed.iter().find(|c| c.directory == save.directory) {
+                    save.size = computed.size;
+                    save.files = computed.files;
+                }
+            }
+            gamedb
+        })?;
+    }
+    Ok(gamedb)
+}
 
-    gamedb.saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-
-    gamedb.saves.iter().for_each(|save| {
-        println!("{} - {}", save.timestamp, save.name);
-    });
-
-    Ok(())
+// Like sizes, the index is stored when a save is created and built on demand for
+// older saves, or when nauttaja has learned to extract more from saves
+fn index_saves(gamedb: &mut GameDB, rebuild: bool) -> Result<usize, CliError> {
+    let mut indexed = Vec::new();
+    for save in gamedb.saves.iter_mut().chain(gamedb.trash.iter_mut()) {
+        let current = save
+            .index
+            .as_ref()
+            .is_some_and(|index| index.version == noita::INDEX_VERSION);
+        if current && !rebuild {
+            continue;
+        }
+        let save_dir = save_path(save)?;
+        if !save_dir.exists() {
+            continue;
+        }
+        save.index = Some(noita::index(&save_dir.join(NOITA_SAVE_DIRECTORY)));
+        indexed.push(save.clone());
+    }
+    if !indexed.is_empty() {
+        update_gamedb(|mut gamedb: GameDB| {
+            for save in gamedb.saves.iter_mut().chain(gamedb.trash.iter_mut()) {
+                if let Some(indexed) = indexed.iter().find(|i| i.directory == save.directory) {
+                    save.index = indexed.index.clone();
+                }
+            }
+            gamedb
+        })?;
+    }
+    Ok(indexed.len())
 }
 
-fn list_trash() -> Result<(), CliError> {
+fn load_gamedb_indexed() -> Result<GameDB, CliError> {
     let mut gamedb = load_gamedb()?;
+    index_saves(&mut gamedb, false)?;
+    Ok(gamedb)
+}
+
+// The total size is always shown, so what the trash takes up is never a surprise
+fn list_trash(config: &Config, options: &ListOptions) -> Result<(), CliError> {
+    let mut gamedb = load_gamedb_with_sizes()?;
+    if options.details || options.min_orbs.is_some() {
+        index_saves(&mut gamedb, false)?;
+    }
+    gamedb.trash.retain(|save| options.includes(save));
+    options.sort(&mut gamedb.trash);
 
+    if output::is_json() {
+        output::emit(&saves_output(
+            &gamedb,
+            &gamedb.trash,
+            config.trash_retention_days,
+        ));
+        return Ok(());
+    }
     if gamedb.trash.is_empty() {
-        println!("No saves found");
+        say!("No saves found");
         return Ok(());
     }
 
-    gamedb.trash.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-
-    gamedb.trash.iter().for_each(|save| {
-        println!("{} - {}", save.timestamp, save.name);
-    });
+    print_saves(
+        config,
+        &gamedb,
+        &gamedb.trash,
+        options,
+        config.trash_retention_days,
+    );
+    show!("Total {}", total_size(&gamedb.trash));
 
     Ok(())
 }
 
-fn copy_dir<A, B>(from: A, to: B) -> Result<(), CliError>
+fn copy_dir<A, B>(config: &Config, from: A, to: B) -> Result<copy::Engine, CliError>
 where
     A: AsRef<Path>,
     B: AsRef<Path>,
 {
-    let options = dir::CopyOptions::new();
-    let mut from_paths = Vec::new();
-    from_paths.push(from);
-    fs_extra::copy_items(&from_paths, to, &options)?;
+    copy_with(&config.copy_engines, from.as_ref(), to.as_ref())
+}
+
+// Copies a save or backup into Noitas directory
+fn copy_into_noita<A>(config: &Config, from: A) -> Result<(), CliError>
+where
+    A: AsRef<Path>,
+{
+    copy_with(
+        &noita_chain(config),
+        from.as_ref(),
+        Path::new(&config.noita_root_dir),
+    )?;
     Ok(())
 }
 
+// The engines for copies to and from Noitas directory, or any other directory
+// nauttaja does not own. Engines sharing files with the original are passed
+// over, since Noita would then write into the files of saves and backups, or
+// making a save read-only would make the current save read-only as well
+fn noita_chain(config: &Config) -> Vec<copy::Engine> {
+    config
+        .copy_engines
+        .iter()
+        .copied()
+        .filter(|engine| !engine.shares_files())
+        .collect()
+}
+
+fn copy_with(chain: &[copy::Engine], from: &Path, to: &Path) -> Result<copy::Engine, CliError> {
+    let started = Instant::now();
+    let engine = copy::copy_dir(chain, from, to)?;
+    info!(
+        "Copied [{}] into [{}] with the {} engine in {:.2?}",
+        from.display(),
+        to.display(),
+        engine.name(),
+        started.elapsed()
+    );
+    Ok(engine)
+}
+
+// Copies a directory into a new save, sharing its files with every other save
+// having them through the store, and returns the manifest of the new save
+fn store_copy(from: &Path, to: &Path) -> Result<manifest::Manifest, CliError> {
+    let started = Instant::now();
+    let (manifest, shared) = store::copy_dir(from, to)?;
+    info!(
+        "Copied [{}] into [{}] in {:.2?}, sharing {} files with other saves",
+        from.display(),
+        to.display(),
+        started.elapsed(),
+        shared
+    );
+    Ok(manifest)
+}
+
+// Files of stored saves are shared with other saves through the store, which
+// keeps them read-only, so only the directories of stored saves are changed
+fn set_save_readonly(save_dir: &Path, stored: bool, readonly: bool) -> Result<(), CliError> {
+    if stored {
+        copy::set_dirs_readonly(save_dir, readonly)
+    } else {
+        copy::set_readonly(save_dir, readonly)
+    }
+}
+
+fn collect_store_garbage() -> Result<(usize, u64), CliError> {
+    let gamedb = load_gamedb()?;
+    let mut save_dirs = Vec::new();
+    for save in gamedb.saves.iter().chain(&gamedb.trash) {
+        if save.stored {
+            save_dirs.push(save_path(save)?);
+        }
+    }
+    store::collect_garbage(&save_dirs)
+}
+
 fn nauttaja_dir() -> std::io::Result<PathBuf> {
+    if let Some(storage_root) = env::var_os("NAUTTAJA_STORAGE_ROOT") {
+        return Ok(PathBuf::from(storage_root));
+    }
     home::home_dir()
         .ok_or(Error::new(
             ErrorKind::NotFound,
@@ -440,18 +5329,31 @@ fn nauttaja_dir() -> std::io::Result<PathBuf> {
         .map(|home_dir| home_dir.as_path().join(NAUTTAJA_DIRECTORY))
 }
 
+fn generate_manifests(roots: &[PathBuf]) -> Vec<Result<manifest::Manifest, CliError>> {
+    let manifests = manifest::generate_many(roots, |update| progress::report("hash", update));
+    if !roots.is_empty() {
+        progress::finish("hash");
+    }
+    manifests
+}
+
+fn save_path(save: &Save) -> Result<PathBuf, CliError> {
+    Ok(nauttaja_dir()?
+        .join(NAUTTAJA_SAVES_DIRECTORY)
+        .join(&save.directory))
+}
+
 fn noita_save_dir(config: &Config) -> PathBuf {
-    PathBuf::from(format!(
-        "{}\\{}",
-        config.noita_root_dir, NOITA_SAVE_DIRECTORY
-    ))
+    Path::new(&config.noita_root_dir).join(NOITA_SAVE_DIRECTORY)
 }
 
 fn load_gamedb() -> Result<GameDB, CliError> {
     if let Ok(dir) = nauttaja_dir() {
-        let data = fs::read_to_string(dir.join(NAUTTAJA_GAMEDB_FILE))?;
-        let config = serde_json::from_str(&data)?;
-        Ok(config)
+        let gamedb_file = dir.join(NAUTTAJA_GAMEDB_FILE);
+        if !gamedb_file.exists() {
+            return Ok(GameDB::default());
+        }
+        gamedb::load(&gamedb_file)
     } else {
         Err(CliError::Io(Error::new(
             ErrorKind::NotFound,
@@ -460,10 +5362,34 @@ fn load_gamedb() -> Result<GameDB, CliError> {
     }
 }
 
+// Positions reported by serde_json are relative to the parsed text, which
+// starts at the given line and column of the file
+fn describe_json_error(error: &serde_json::Error, (line, column): (usize, usize)) -> String {
+    let message = error.to_string();
+    let message = match message.rfind(" at line ") {
+        Some(index) => &message[..index],
+        None => &message,
+    };
+    let error_line = error.line().max(1);
+    let error_column = if error_line == 1 {
+        column + error.column().max(1) - 1
+    } else {
+        error.column()
+    };
+    format!(
+        "line {}, column {}: {}",
+        line + error_line - 1,
+        error_column,
+        message
+    )
+}
+
 fn uuid() -> String {
     Uuid::new_v4().to_hyphenated().to_string()
 }
 
+// Not waited for, which would keep nauttaja open until Explorer is closed
+#[allow(clippy::zombie_processes)]
 fn open_explorer_in(dir: &str) {
     Command::new("explorer")
         .arg(dir)
@@ -471,6 +5397,286 @@ fn open_explorer_in(dir: &str) {
         .expect("Could not open explorer");
 }
 
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn export_config(config: &Config, file: &str) -> Result<(), CliError> {
+    let count = config::export(config, Path::new(file))?;
+    say!("Exported {} settings to [{}]", count, file);
+    say!("The paths to Noita and Steam are left out, since they differ between machines");
+    Ok(())
+}
+
+fn import_config(config: &Config, file: &str) -> Result<(), CliError> {
+    let import = match config::import(config, Path::new(file)) {
+        Ok(import) => import,
+        Err(CliError::Config(message)) => {
+            say!("{}", message);
+            return Ok(());
+        }
+        Err(error) => return Err(error),
+    };
+    for key in &import.kept {
+        say!("Keeping [{}] of this machine", key);
+    }
+    if import.changes.is_empty() {
+        say!("The settings in [{}] are already in use", file);
+        return Ok(());
+    }
+    let show = |value: &Option<serde_json::Value>| match value {
+        Some(value) => value.to_string(),
+        None => "nothing".to_string(),
+    };
+    for (key, current, new) in &import.changes {
+        say!("Changing [{}] from {} to {}", key, show(current), show(new));
+    }
+    let config_file = config::config_file()?;
+    if !confirm(&format!(
+        "Replace the settings in [{}]?",
+        config_file.display()
+    ))? {
+        say!("Aborted, the settings have not been touched");
+        return Ok(());
+    }
+    config::write(&import.config)?;
+    say!(
+        "Imported {} changed settings from [{}]",
+        import.changes.len(),
+        file
+    );
+    Ok(())
+}
+
+fn confirm(message: &str) -> io::Result<bool> {
+    if assume_yes() {
+        say!("{} [y/N] y, answered by --yes", message);
+        return Ok(true);
+    }
+    let answer = prompt(&format!("{} [y/N]", message))?;
+    Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn prompt(message: &str) -> io::Result<String> {
+    if output::is_json() {
+        eprint!("{} ", message);
+        io::stderr().flush()?;
+    } else {
+        print!("{} ", message);
+        io::stdout().flush()?;
+    }
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+// Names of the saves most like the given name, the closest first
+fn closest_names<'a, I>(name: &str, saves: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a Save>,
+{
+    let name = name.to_lowercase();
+    let mut scored: Vec<(f64, &str)> = saves
+        .into_iter()
+        .map(|save| {
+            let score = strsim::jaro_winkler(&name, &save.name.to_lowercase());
+            (score, save.name.as_str())
+        })
+        .filter(|(score, _)| *score >= 0.8)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored
+        .into_iter()
+        .take(3)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+// Every way the commands could understand a name, in the order they try them
+fn which(query: &str) -> Result<(), CliError> {
+    let gamedb = load_gamedb()?;
+    let mut saves: Vec<&Save> = gamedb.saves.iter().collect();
+    saves.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    let exact = saves.iter().any(|save| save.name == query);
+
+    let mut resolved: Vec<(&str, Vec<&Save>, String)> = Vec::new();
+    let mut expanded = false;
+    if let Some(save) = saves.iter().find(|save| save.name == query) {
+        resolved.push((
+            "exact name",
+            vec![save],
+            "every command taking a name uses it".to_string(),
+        ));
+    }
+    if let Some(save) = gamedb.trash.iter().find(|save| save.name == query) {
+        resolved.push((
+            "removed save",
+            vec![save],
+            "restore, delete, info, rename and note use it".to_string(),
+        ));
+    }
+    if let Some(pattern) = wildcard(query) {
+        let matched: Vec<&Save> = saves
+            .iter()
+            .copied()
+            .filter(|save| pattern.is_match(&save.name))
+            .collect();
+        if !matched.is_empty() {
+            expanded = true;
+            resolved.push((
+                "wildcard",
+                matched,
+                if exact {
+                    "ignored by remove, restore and delete, which use the exact name instead"
+                } else {
+                    "remove, restore and delete act on all of them"
+                }
+                .to_string(),
+            ));
+        }
+    }
+    if let Ok(slot) = query.parse::<u32>() {
+        if let Some(save) = saves.iter().find(|save| save.slot == Some(slot)) {
+            resolved.push((
+                "quicksave slot",
+                vec![save],
+                format!("nauttaja ql {} loads it", slot),
+            ));
+        }
+    }
+    if let Ok(pattern) = list_pattern(query) {
+        let matched: Vec<&Save> = saves
+            .iter()
+            .copied()
+            .filter(|save| pattern.is_match(&save.name))
+            .collect();
+        if !matched.is_empty() {
+            resolved.push((
+                "list pattern",
+                matched,
+                format!("nauttaja list {} shows them", query),
+            ));
+        }
+    }
+    // Names are only suggested when nothing else resolved them
+    if !exact && !expanded {
+        let close = closest_names(query, &gamedb.saves);
+        let matched: Vec<&Save> = close
+            .iter()
+            .filter_map(|name| saves.iter().copied().find(|save| save.name == *name))
+            .collect();
+        if !matched.is_empty() {
+            resolved.push((
+                "close name",
+                matched,
+                match close.as_slice() {
+                    [_] => "load offers it in place of the name, other commands suggest it",
+                    _ => "commands suggest them when failing to find the name",
+                }
+                .to_string(),
+            ));
+        }
+    }
+
+    if output::is_json() {
+        output::emit(&json!({
+            "query": query,
+            "resolved": resolved
+                .iter()
+                .map(|(rule, saves, effect)| json!({
+                    "rule": rule,
+                    "saves": saves.iter().map(|save| &save.name).collect::<Vec<_>>(),
+                    "effect": effect,
+                }))
+                .collect::<Vec<_>>(),
+        }));
+        return Ok(());
+    }
+    if resolved.is_empty() {
+        show!("[{}] does not resolve to any save", query);
+        return Ok(());
+    }
+    for (rule, saves, effect) in &resolved {
+        let names: Vec<String> = saves
+            .iter()
+            .map(|save| format!("[{}]", save.name))
+            .collect();
+        show!("As {}, [{}] resolves to {}", rule, query, names.join(", "));
+        show!("    {}", effect);
+    }
+    Ok(())
+}
+
+// "Failed to find", along with the closest names when there are any
+fn not_found<'a, I>(name: &str, saves: I) -> String
+where
+    I: IntoIterator<Item = &'a Save>,
+{
+    let suggestions: Vec<String> = closest_names(name, saves)
+        .iter()
+        .map(|name| format!("[{}]", name))
+        .collect();
+    match suggestions.as_slice() {
+        [] => format!("Failed to find [{}]", name),
+        [only] => format!("Failed to find [{}], did you mean {}?", name, only),
+        [rest @ .., last] => format!(
+            "Failed to find [{}], did you mean {} or {}?",
+            name,
+            rest.join(", "),
+            last
+        ),
+    }
+}
+
+// Whether a version of nauttaja is newer than this one
+fn is_newer(version: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split(['.', '-', '+'])
+            .take(3)
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parse(version) > parse(crate_version!())
+}
+
+// Whether two root directories are the same Noita install, even when spelled
+// differently
+fn same_install(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => Path::new(a) == Path::new(b),
+    }
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()
+}
+
+fn age(timestamp: &str) -> Option<String> {
+    let elapsed = Local::now().naive_local() - parse_timestamp(timestamp)?;
+    Some(match elapsed.num_days() {
+        0 => match elapsed.num_hours() {
+            0 => "less than an hour ago".to_string(),
+            1 => "1 hour ago".to_string(),
+            hours => format!("{} hours ago", hours),
+        },
+        1 => "1 day ago".to_string(),
+        days => format!("{} days ago", days),
+    })
+}
+
 fn timestamp() -> String {
     let time = Local::now();
     format!(
@@ -489,8 +5695,24 @@ enum CliError {
     Io(std::io::Error),
     Serde(serde_json::Error),
     FsExtra(fs_extra::error::Error),
+    Config(String),
+    GameDB(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Io(error) => write!(f, "{}", error),
+            CliError::Serde(error) => write!(f, "{}", error),
+            CliError::FsExtra(error) => write!(f, "{}", error),
+            CliError::Config(message) => write!(f, "{}", message),
+            CliError::GameDB(message) => write!(f, "{}", message),
+        }
+    }
 }
 
+impl std::error::Error for CliError {}
+
 impl From<std::io::Error> for CliError {
     fn from(error: std::io::Error) -> Self {
         CliError::Io(error)
@@ -0,0 +1,253 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
+
+use fs_extra::dir;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::progress::Update;
+use crate::{compress, CliError};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+impl Manifest {
+    /// Identifies the contents of the files below `prefix`, regardless of
+    /// where they are stored.
+    pub fn digest(&self, prefix: &str) -> String {
+        let mut hasher = blake3::Hasher::new();
+        for entry in &self.files {
+            if let Some(path) = entry.path.strip_prefix(prefix) {
+                hasher.update(path.as_bytes());
+                hasher.update(b"\0");
+                hasher.update(entry.hash.as_bytes());
+                hasher.update(b"\n");
+            }
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    /// The files below `prefix`, with paths relative to it.
+    pub fn subtree(&self, prefix: &str) -> Manifest {
+        Manifest {
+            files: self
+                .files
+                .iter()
+                .filter_map(|entry| {
+                    entry.path.strip_prefix(prefix).map(|path| ManifestEntry {
+                        path: path.to_string(),
+                        ..entry.clone()
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+pub struct Verification {
+    pub missing: Vec<String>,
+    pub changed: Vec<String>,
+    pub unexpected: Vec<String>,
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.changed.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+pub fn generate(root: &Path) -> Result<Manifest, CliError> {
+    let started = Instant::now();
+    let manifest = generate_many(&[root.to_path_buf()], |_| {})
+        .pop()
+        .expect("One manifest per root")?;
+    debug!(
+        "Hashed {} files in [{}] in {:.2?}",
+        manifest.files.len(),
+        root.display(),
+        started.elapsed()
+    );
+    Ok(manifest)
+}
+
+/// Hashes every file below each root (except a manifest stored there) using a
+/// single bounded pool of worker threads shared by all roots. Files are
+/// streamed through the hasher, so memory usage stays flat regardless of how
+/// large the individual files are. `on_progress` is called after every
+/// hashed file.
+pub fn generate_many<F>(roots: &[PathBuf], on_progress: F) -> Vec<Result<Manifest, CliError>>
+where
+    F: Fn(&Update) + Sync,
+{
+    let mut jobs = Vec::new();
+    let mut listings = Vec::with_capacity(roots.len());
+    for (index, root) in roots.iter().enumerate() {
+        match list_files(root) {
+            Ok(files) => {
+                jobs.extend(files.into_iter().map(|(file, size)| (index, file, size)));
+                listings.push(Ok(()));
+            }
+            Err(error) => listings.push(Err(error)),
+        }
+    }
+
+    let total_bytes = jobs.iter().map(|(_, _, size)| size).sum();
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let bytes = AtomicU64::new(0);
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(jobs.len())
+        .max(1);
+
+    let hashed: Vec<Vec<(usize, Result<ManifestEntry, CliError>)>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut entries = Vec::new();
+                    while let Some((root, path, size)) =
+                        jobs.get(next.fetch_add(1, Ordering::Relaxed))
+                    {
+                        entries.push((*root, hash_file(&roots[*root], path)));
+                        on_progress(&Update {
+                            files: done.fetch_add(1, Ordering::Relaxed) + 1,
+                            total_files: jobs.len(),
+                            bytes: bytes.fetch_add(*size, Ordering::Relaxed) + size,
+                            total_bytes,
+                            file: path,
+                        });
+                    }
+                    entries
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Hashing worker panicked"))
+            .collect()
+    });
+
+    let mut manifests: Vec<Result<Manifest, CliError>> = listings
+        .into_iter()
+        .map(|listing| listing.map(|_| Manifest::default()))
+        .collect();
+    for (root, entry) in hashed.into_iter().flatten() {
+        if let Ok(manifest) = &mut manifests[root] {
+            match entry {
+                Ok(entry) => manifest.files.push(entry),
+                Err(error) => manifests[root] = Err(error),
+            }
+        }
+    }
+    for manifest in manifests.iter_mut().flatten() {
+        manifest.files.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+    manifests
+}
+
+pub fn read(root: &Path) -> Result<Option<Manifest>, CliError> {
+    let file = root.join(MANIFEST_FILE);
+    if !file.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&fs::read_to_string(file)?)?))
+}
+
+pub fn compare(expected: &Manifest, actual: &Manifest) -> Verification {
+    let mut verification = Verification {
+        missing: Vec::new(),
+        changed: Vec::new(),
+        unexpected: Vec::new(),
+    };
+    for entry in &expected.files {
+        match find(actual, &entry.path) {
+            Some(other) if other == entry => {}
+            Some(_) => verification.changed.push(entry.path.clone()),
+            None => verification.missing.push(entry.path.clone()),
+        }
+    }
+    for entry in &actual.files {
+        if find(expected, &entry.path).is_none() {
+            verification.unexpected.push(entry.path.clone());
+        }
+    }
+    verification
+}
+
+pub fn write(root: &Path, manifest: &Manifest) -> Result<(), CliError> {
+    fs::write(root.join(MANIFEST_FILE), serde_json::to_string(manifest)?)?;
+    Ok(())
+}
+
+fn find<'a>(manifest: &'a Manifest, path: &str) -> Option<&'a ManifestEntry> {
+    manifest
+        .files
+        .binary_search_by(|entry| entry.path.as_str().cmp(path))
+        .ok()
+        .map(|index| &manifest.files[index])
+}
+
+fn list_files(root: &Path) -> Result<Vec<(PathBuf, u64)>, CliError> {
+    let content = dir::get_dir_content(root)?;
+    let manifest_file = root.join(MANIFEST_FILE);
+    content
+        .files
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|path| *path != manifest_file)
+        .map(|path| {
+            let size = fs::metadata(&path)?.len();
+            Ok((path, size))
+        })
+        .collect()
+}
+
+pub fn hash(path: &Path) -> Result<String, CliError> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Hashes a single file into its manifest entry, with its path relative to
+/// `root`. Compressed files are hashed as the file they were compressed from,
+/// so a manifest does not change when a save is compressed.
+pub fn hash_file(root: &Path, path: &Path) -> Result<ManifestEntry, CliError> {
+    let mut hasher = blake3::Hasher::new();
+    let original = path
+        .to_str()
+        .and_then(|path| path.strip_suffix(compress::SUFFIX))
+        .map(Path::new);
+    let size = match original {
+        Some(_) => compress::read(path, |reader| {
+            hasher.update_reader(reader).map(|hasher| hasher.count())
+        })?,
+        None => hasher.update_reader(File::open(path)?)?.count(),
+    };
+    let path = original.unwrap_or(path);
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let relative = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Ok(ManifestEntry {
+        path: relative,
+        size,
+        hash: hasher.finalize().to_hex().to_string(),
+    })
+}
@@ -0,0 +1,283 @@
+use std::fs;
+use std::path::Path;
+
+use roxmltree::Document;
+use serde::{Deserialize, Serialize};
+
+use crate::NOITA_RUN_FILES;
+
+/// Bumped whenever more is extracted from saves, so existing indexes are rebuilt
+pub const INDEX_VERSION: u32 = 3;
+
+// Where each biome along the main path roughly begins, in pixels below the surface
+const BIOMES: &[(f64, &str)] = &[
+    (0.0, "Mines"),
+    (1400.0, "Coal Pits"),
+    (2950.0, "Snowy Depths"),
+    (5000.0, "Hiisi Base"),
+    (6500.0, "Underground Jungle"),
+    (8550.0, "The Vault"),
+    (10600.0, "Temple of the Art"),
+    (12650.0, "The Laboratory"),
+];
+// Flags set in the world state when a boss dies, and the name of the boss
+const BOSSES: &[(&str, &str)] = &[
+    ("boss_centipede", "Kolmisilmä"),
+    ("boss_dragon", "Suomuhauki"),
+    ("boss_limbs", "Kolmisilmän koipi"),
+    ("boss_alchemist", "Ylialkemisti"),
+    ("boss_ghost", "Unohdettu"),
+    ("boss_meat", "Kolmisilmän sydän"),
+    ("boss_robot", "Kolmisilmän silmä"),
+    ("boss_wizard", "Mestarien mestari"),
+    ("boss_fish", "Syväolento"),
+];
+// The main path is narrow, anything further to the sides is another biome
+const MAIN_PATH_HALF_WIDTH: f64 = 4000.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunProgress {
+    pub depth: f64,
+    pub biome: String,
+    pub orbs: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bosses: Vec<String>,
+    /// Health of the player, as shown in game
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hp: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_hp: Option<f64>,
+}
+
+/// Reads a rough indication of how far the run in a save has progressed,
+/// `None` if there is no run or its files cannot be understood.
+pub fn run_progress(save00: &Path) -> Option<RunProgress> {
+    let player = fs::read_to_string(save00.join(NOITA_RUN_FILES[0])).ok()?;
+    let player = Document::parse(&player).ok()?;
+    let transform = player
+        .descendants()
+        .find(|node| node.has_tag_name("_Transform"))?;
+    let x: f64 = transform.attribute("position.x")?.parse().ok()?;
+    let depth: f64 = transform.attribute("position.y")?.parse().ok()?;
+    // Noita stores health in units of 25 of what it shows
+    let damage_model = player
+        .descendants()
+        .find(|node| node.has_tag_name("DamageModelComponent"));
+    let health = |attribute: &str| {
+        damage_model
+            .and_then(|component| component.attribute(attribute))
+            .and_then(|value| value.parse::<f64>().ok())
+            .map(|value| value * 25.0)
+    };
+
+    let biome = if depth < 0.0 {
+        "Surface"
+    } else if x.abs() > MAIN_PATH_HALF_WIDTH {
+        "off the main path"
+    } else {
+        BIOMES
+            .iter()
+            .rev()
+            .find(|(start, _)| depth >= *start)
+            .map(|(_, biome)| *biome)
+            .unwrap_or(BIOMES[0].1)
+    };
+
+    Some(RunProgress {
+        depth,
+        biome: biome.to_string(),
+        orbs: orbs_found(save00),
+        bosses: bosses_killed(save00),
+        hp: health("hp"),
+        max_hp: health("max_hp"),
+    })
+}
+
+fn orbs_found(save00: &Path) -> Option<usize> {
+    let world_state = fs::read_to_string(save00.join(NOITA_RUN_FILES[1])).ok()?;
+    let world_state = Document::parse(&world_state).ok()?;
+    let orbs = world_state
+        .descendants()
+        .find(|node| node.has_tag_name("orbs_found_thisrun"))?;
+    Some(orbs.children().filter(|node| node.is_element()).count())
+}
+
+fn bosses_killed(save00: &Path) -> Vec<String> {
+    let flags = world_state_flags(save00).unwrap_or_default();
+    BOSSES
+        .iter()
+        .filter(|(flag, _)| flags.iter().any(|set| set.starts_with(flag)))
+        .map(|(_, boss)| boss.to_string())
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Wand {
+    pub name: String,
+    pub capacity: Option<u32>,
+    pub spells: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Inventory {
+    pub wands: Vec<Wand>,
+    pub spells: Vec<String>,
+    pub perks: Vec<String>,
+}
+
+/// Lists the wands, loose spells and perks of the player in a save, `None` if
+/// there is no run or its files cannot be understood. Spells and perks are
+/// named by their ids, such as `LIGHT_BULLET` and `CRITICAL_HIT`.
+pub fn inventory(save00: &Path) -> Option<Inventory> {
+    let player = fs::read_to_string(save00.join(NOITA_RUN_FILES[0])).ok()?;
+    let player = Document::parse(&player).ok()?;
+    let root = player.root_element();
+    let child_entity = |name: &str| {
+        root.children()
+            .find(|node| node.has_tag_name("Entity") && node.attribute("name") == Some(name))
+    };
+
+    let mut wands = Vec::new();
+    if let Some(quick) = child_entity("inventory_quick") {
+        for item in quick.children().filter(|node| node.has_tag_name("Entity")) {
+            let ability = match item
+                .children()
+                .find(|node| node.has_tag_name("AbilityComponent"))
+            {
+                Some(ability) => ability,
+                None => continue,
+            };
+            let name = ability
+                .attribute("ui_name")
+                .map(|name| name.trim_start_matches('$').to_string())
+                .unwrap_or_else(|| "wand".to_string());
+            let capacity = ability
+                .children()
+                .find(|node| node.has_tag_name("gun_config"))
+                .and_then(|config| config.attribute("deck_capacity"))
+                .and_then(|capacity| capacity.parse().ok());
+            wands.push(Wand {
+                name,
+                capacity,
+                spells: spells_in(item),
+            });
+        }
+    }
+    let spells = child_entity("inventory_full")
+        .map(spells_in)
+        .unwrap_or_default();
+
+    let mut perks: Vec<String> = root
+        .descendants()
+        .filter(|node| {
+            node.has_tag_name("UIIconComponent") && node.attribute("is_perk") == Some("1")
+        })
+        .filter_map(|node| node.attribute("name"))
+        .map(|name| name.trim_start_matches("$perk_").to_uppercase())
+        .collect();
+    if let Some(flags) = world_state_flags(save00) {
+        for flag in flags {
+            if let Some(perk) = flag.strip_prefix("PERK_PICKED_") {
+                if !perks.iter().any(|known| known == perk) {
+                    perks.push(perk.to_string());
+                }
+            }
+        }
+    }
+
+    Some(Inventory {
+        wands,
+        spells,
+        perks,
+    })
+}
+
+// Spells are entities below the wand or inventory, each with an action id
+fn spells_in(container: roxmltree::Node) -> Vec<String> {
+    container
+        .children()
+        .filter(|node| node.has_tag_name("Entity"))
+        .filter_map(|item| {
+            item.children()
+                .find(|node| node.has_tag_name("ItemActionComponent"))
+                .and_then(|action| action.attribute("action_id"))
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+fn world_state_flags(save00: &Path) -> Option<Vec<String>> {
+    let world_state = fs::read_to_string(save00.join(NOITA_RUN_FILES[1])).ok()?;
+    let world_state = Document::parse(&world_state).ok()?;
+    let flags = world_state
+        .descendants()
+        .find(|node| node.has_tag_name("flags"))?;
+    Some(
+        flags
+            .children()
+            .filter_map(|node| node.attribute("value"))
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionStats {
+    pub seed: Option<String>,
+    pub playtime_secs: Option<f64>,
+    pub gold: Option<u64>,
+    pub kills: Option<u64>,
+}
+
+/// Reads the statistics Noita keeps for the latest session of the run, from
+/// `stats/sessions/<date>_stats.xml`.
+pub fn session_stats(save00: &Path) -> Option<SessionStats> {
+    let sessions = save00.join("stats").join("sessions");
+    let latest = fs::read_dir(sessions)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with("_stats.xml"))
+        })
+        .max()?;
+    let stats = fs::read_to_string(latest).ok()?;
+    let stats = Document::parse(&stats).ok()?;
+    let stats = stats
+        .descendants()
+        .find(|node| node.has_tag_name("stats"))
+        .unwrap_or_else(|| stats.root_element());
+    Some(SessionStats {
+        seed: stats.attribute("world_seed").map(str::to_string),
+        playtime_secs: stats
+            .attribute("playtime")
+            .and_then(|value| value.parse().ok()),
+        gold: stats.attribute("gold").and_then(|value| value.parse().ok()),
+        kills: stats
+            .attribute("enemies_killed")
+            .and_then(|value| value.parse().ok()),
+    })
+}
+
+/// Everything extracted from the files of a save, cached in the gamedb so
+/// queries do not have to parse the save again.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveIndex {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<RunProgress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inventory: Option<Inventory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<SessionStats>,
+}
+
+pub fn index(save00: &Path) -> SaveIndex {
+    SaveIndex {
+        version: INDEX_VERSION,
+        progress: run_progress(save00),
+        inventory: inventory(save00),
+        stats: session_stats(save00),
+    }
+}
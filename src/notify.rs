@@ -0,0 +1,47 @@
+use std::process::Command;
+
+use crate::CliError;
+
+/// Shows a desktop notification, without waiting for it to be dismissed.
+#[cfg(windows)]
+pub fn send(title: &str, body: &str) -> Result<(), CliError> {
+    let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+    // The icon has to stay around for the balloon to be shown
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $icon = New-Object System.Windows.Forms.NotifyIcon; \
+         $icon.Icon = [System.Drawing.SystemIcons]::Information; \
+         $icon.Visible = $true; \
+         $icon.ShowBalloonTip(10000, {}, {}, 'Info'); \
+         Start-Sleep -Seconds 10; \
+         $icon.Dispose()",
+        quote(title),
+        quote(body)
+    );
+    Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn send(title: &str, body: &str) -> Result<(), CliError> {
+    let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "display notification {} with title {}",
+            quote(body),
+            quote(title)
+        ))
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn send(title: &str, body: &str) -> Result<(), CliError> {
+    Command::new("notify-send")
+        .args(["--app-name", "nauttaja", title, body])
+        .spawn()?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::LevelFilter;
+use ratatui::crossterm::style::StyledContent;
+use serde::Serialize;
+
+static JSON: AtomicBool = AtomicBool::new(false);
+static COLOR: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Switches to JSON output when asked to by `--json` or `NAUTTAJA_OUTPUT=json`.
+/// Commands then write their results to stdout as JSON, while messages meant
+/// for people are written to stderr.
+pub fn set_json(flag: bool) {
+    let json = flag || env::var("NAUTTAJA_OUTPUT").is_ok_and(|output| output == "json");
+    JSON.store(json, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Colors output meant for people when it goes to a terminal, unless turned
+/// off by `--no-color` or the `NO_COLOR` environment variable.
+pub fn set_color(flag: bool) {
+    let color = flag
+        && !is_json()
+        && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && io::stdout().is_terminal();
+    COLOR.store(color, Ordering::Relaxed);
+}
+
+/// Styles the text when output is colored, leaving it as it is otherwise.
+pub fn styled<F>(text: String, style: F) -> String
+where
+    F: FnOnce(String) -> StyledContent<String>,
+{
+    if COLOR.load(Ordering::Relaxed) {
+        style(text).to_string()
+    } else {
+        text
+    }
+}
+
+/// Writes a result as a single line of JSON to stdout.
+pub fn emit<T: Serialize>(value: &T) {
+    match serde_json::to_string(value) {
+        Ok(json) => println!("{}", json),
+        Err(error) => eprintln!("Failed to write JSON: {}", error),
+    }
+}
+
+/// Sets up logging to stderr, which tells which paths are touched and how long
+/// things take. Warnings are logged by default, `-v` adds what is done and
+/// `-vv` how it is done, while `-q` silences logging along with every message.
+/// `NAUTTAJA_LOG` overrides the level, using the syntax of `RUST_LOG`.
+pub fn init_logging(verbosity: u64, quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    let level = match (quiet, verbosity) {
+        (true, _) => LevelFilter::Off,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_env(env_logger::Env::new().filter("NAUTTAJA_LOG"))
+        .format(|buf, record| writeln!(buf, "{:<5} {}", record.level(), record.args()))
+        .init();
+}
+
+/// Whether messages are silenced by `-q`, leaving only prompts and JSON results.
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
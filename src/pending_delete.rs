@@ -0,0 +1,141 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{Duration, Local, NaiveDateTime};
+use fs_extra::dir;
+use log::info;
+
+use crate::{
+    confirm, fault, human_size, nauttaja_dir, save_path, set_save_readonly, CliError, Config, Save,
+    BACKUP_NAME_FORMAT,
+};
+
+const NAUTTAJA_PENDING_DELETE_DIRECTORY: &str = "pending-delete";
+const SAVE_ENTRY_FILE: &str = "save.json";
+
+fn pending_delete_dir() -> Result<PathBuf, CliError> {
+    Ok(nauttaja_dir()?.join(NAUTTAJA_PENDING_DELETE_DIRECTORY))
+}
+
+/// Moves the files of a deleted save into the pending-delete area, along with
+/// its gamedb entry, where they are kept until the grace period has passed.
+/// Without a grace period the files are deleted right away.
+pub fn delete(config: &Config, save: &Save) -> Result<(), CliError> {
+    let save_dir = save_path(save)?;
+    if !save_dir.exists() {
+        return Ok(());
+    }
+    set_save_readonly(&save_dir, save.stored, false)?;
+    if config.delete_grace_days == 0 {
+        info!("Deleting [{}]", save_dir.display());
+        fs::remove_dir_all(save_dir)?;
+        return Ok(());
+    }
+
+    let pending_dir = pending_delete_dir()?;
+    fs::create_dir_all(&pending_dir)?;
+    let target = pending_dir.join(format!(
+        "{}_{}",
+        Local::now().format(BACKUP_NAME_FORMAT),
+        save.directory
+    ));
+    info!("Moving [{}] to [{}]", save_dir.display(), target.display());
+    fs::rename(&save_dir, &target)?;
+    fault::check("delete")?;
+    fs::write(
+        target.join(SAVE_ENTRY_FILE),
+        serde_json::to_string_pretty(save)?,
+    )?;
+    Ok(())
+}
+
+/// What [`delete`] would do with the files of a save, for --dry-run.
+pub fn describe(config: &Config, save: &Save) -> Result<String, CliError> {
+    let save_dir = save_path(save)?;
+    Ok(if !save_dir.exists() {
+        format!("The files of [{}] are already gone", save.name)
+    } else if config.delete_grace_days == 0 {
+        format!("Would delete [{}]", save_dir.display())
+    } else {
+        format!(
+            "Would move [{}] to [{}], where it is kept for {} days",
+            save_dir.display(),
+            pending_delete_dir()?.display(),
+            config.delete_grace_days
+        )
+    })
+}
+
+// Every entry along with when it was deleted, oldest first
+fn pending() -> Result<Vec<(PathBuf, Option<NaiveDateTime>)>, CliError> {
+    let pending_dir = pending_delete_dir()?;
+    if !pending_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut pending = Vec::new();
+    for entry in fs::read_dir(pending_dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let deleted = name
+            .get(..19)
+            .and_then(|prefix| NaiveDateTime::parse_from_str(prefix, BACKUP_NAME_FORMAT).ok());
+        pending.push((path, deleted));
+    }
+    pending.sort();
+    Ok(pending)
+}
+
+pub fn pending_count() -> Result<usize, CliError> {
+    Ok(pending()?.len())
+}
+
+pub fn purge_expired(config: &Config) -> Result<usize, CliError> {
+    let cutoff = Local::now().naive_local() - Duration::days(config.delete_grace_days as i64);
+    let mut purged = 0;
+    for (path, deleted) in pending()? {
+        if deleted.is_some_and(|deleted| deleted <= cutoff) {
+            fs::remove_dir_all(path)?;
+            purged += 1;
+        }
+    }
+    if purged > 0 {
+        say!(
+            "Purged {} deleted saves whose grace period has passed",
+            purged
+        );
+    }
+    Ok(purged)
+}
+
+pub fn purge_all() -> Result<(), CliError> {
+    let pending = pending()?;
+    if pending.is_empty() {
+        say!("No deleted saves are pending");
+        return Ok(());
+    }
+    let mut size = 0;
+    for (path, _) in &pending {
+        size += dir::get_size(path)?;
+    }
+    say!(
+        "{} deleted saves are pending, taking up {}",
+        pending.len(),
+        human_size(size)
+    );
+    if !confirm("Purge them now? They cannot be recovered afterwards")? {
+        say!("Aborted, nothing was purged");
+        return Ok(());
+    }
+    for (path, _) in &pending {
+        fs::remove_dir_all(path)?;
+    }
+    say!(
+        "Purged {} deleted saves, {} freed",
+        pending.len(),
+        human_size(size)
+    );
+    Ok(())
+}
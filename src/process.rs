@@ -0,0 +1,49 @@
+use std::io::Error;
+use std::process::Command;
+
+use crate::CliError;
+
+/// Name of the Noita executable, also when running through Wine or Proton
+pub const NOITA_PROCESS: &str = "noita.exe";
+
+/// Whether a process with the given executable name is running.
+#[cfg(windows)]
+pub fn is_running(name: &str) -> Result<bool, CliError> {
+    let output = Command::new("tasklist")
+        .args([
+            "/FI",
+            &format!("IMAGENAME eq {}", name),
+            "/FO",
+            "CSV",
+            "/NH",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(CliError::Io(Error::other(format!(
+            "tasklist failed with {}",
+            output.status
+        ))));
+    }
+    let quoted = format!("\"{}\"", name.to_lowercase());
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|line| line.to_lowercase().starts_with(&quoted)))
+}
+
+/// Whether a process with the given executable name is running.
+#[cfg(not(windows))]
+pub fn is_running(name: &str) -> Result<bool, CliError> {
+    // Wine names its processes after the Windows executable they run
+    let status = Command::new("pgrep")
+        .args(["-i", "-x", name])
+        .output()?
+        .status;
+    match status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => Err(CliError::Io(Error::other(format!(
+            "pgrep failed with {}",
+            status
+        )))),
+    }
+}
@@ -0,0 +1,164 @@
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use ratatui::crossterm::terminal;
+use serde_json::json;
+
+use crate::{human_size, output};
+
+// Characters taken up by the bar itself, between the brackets
+const BAR_WIDTH: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Bar,
+    Json,
+    Off,
+}
+
+pub struct Update<'a> {
+    pub files: usize,
+    pub total_files: usize,
+    pub bytes: u64,
+    pub total_bytes: u64,
+    pub file: &'a Path,
+}
+
+static MODE: OnceLock<Mode> = OnceLock::new();
+// Files and tenths of a percent last reported, copying reports every chunk
+static LAST: Mutex<(usize, u64)> = Mutex::new((0, u64::MAX));
+
+/// Chooses how progress is shown, by default a progress bar when stdout is a
+/// terminal, JSON events with --json and nothing otherwise, so scripts only
+/// get events when they ask for them. Progress is always written to stderr.
+pub fn set_mode(mode: Option<Mode>) {
+    let mode = mode.unwrap_or_else(|| {
+        if output::is_json() {
+            Mode::Json
+        } else if io::stdout().is_terminal() {
+            Mode::Bar
+        } else {
+            Mode::Off
+        }
+    });
+    let _ = MODE.set(mode);
+}
+
+fn mode() -> Mode {
+    let mode = *MODE.get_or_init(|| Mode::Off);
+    if mode == Mode::Bar && !io::stderr().is_terminal() {
+        Mode::Off
+    } else {
+        mode
+    }
+}
+
+pub fn parse_mode(mode: &str) -> Option<Mode> {
+    match mode {
+        "bar" => Some(Mode::Bar),
+        "json" => Some(Mode::Json),
+        "none" => Some(Mode::Off),
+        _ => None,
+    }
+}
+
+pub fn report(operation: &str, update: &Update) {
+    let percent = if update.total_bytes > 0 {
+        update.bytes as f64 / update.total_bytes as f64 * 100.0
+    } else if update.total_files > 0 {
+        update.files as f64 / update.total_files as f64 * 100.0
+    } else {
+        100.0
+    };
+    let current = (update.files, (percent * 10.0) as u64);
+    {
+        let mut last = LAST.lock().unwrap_or_else(|error| error.into_inner());
+        if *last == current {
+            return;
+        }
+        *last = current;
+    }
+
+    match mode() {
+        Mode::Bar => eprint!("\r{}\x1b[K", bar(operation, update, percent)),
+        Mode::Json => {
+            eprintln!(
+                "{}",
+                json!({
+                    "event": "progress",
+                    "operation": operation,
+                    "percent": (percent * 10.0).round() / 10.0,
+                    "files": update.files,
+                    "total_files": update.total_files,
+                    "bytes": update.bytes,
+                    "total_bytes": update.total_bytes,
+                    "file": update.file.display().to_string(),
+                })
+            );
+        }
+        Mode::Off => {}
+    }
+}
+
+pub fn finish(operation: &str) {
+    *LAST.lock().unwrap_or_else(|error| error.into_inner()) = (0, u64::MAX);
+    match mode() {
+        Mode::Bar => eprintln!(),
+        Mode::Json => eprintln!("{}", json!({ "event": "done", "operation": operation })),
+        Mode::Off => {}
+    }
+}
+
+fn label(operation: &str) -> &str {
+    match operation {
+        "hash" => "Hashing",
+        "copy" => "Copying",
+        "backup" => "Backing up",
+        other => other,
+    }
+}
+
+// Such as "Copying [=====     ] 52% 1.2 GiB / 2.3 GiB  world_state.xml", where
+// the file name is shortened from the left to fit the terminal
+fn bar(operation: &str, update: &Update, percent: f64) -> String {
+    let filled = ((percent / 100.0 * BAR_WIDTH as f64) as usize).min(BAR_WIDTH);
+    let amount = if update.total_bytes > 0 {
+        format!(
+            "{} / {}",
+            human_size(update.bytes),
+            human_size(update.total_bytes)
+        )
+    } else {
+        format!("{} / {} files", update.files, update.total_files)
+    };
+    let mut line = format!(
+        "{} [{}{}] {:>3.0}% {}",
+        label(operation),
+        "=".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        percent,
+        amount
+    );
+
+    let columns = terminal::size().map_or(80, |(columns, _)| columns as usize);
+    let file: Vec<char> = update
+        .file
+        .file_name()
+        .unwrap_or(update.file.as_os_str())
+        .to_string_lossy()
+        .chars()
+        .collect();
+    // Kept off the last column, where some terminals wrap the line
+    let room = columns.saturating_sub(line.chars().count() + 3);
+    if room > 3 {
+        line.push_str("  ");
+        if file.len() > room {
+            line.push('…');
+            line.extend(&file[file.len() - (room - 1)..]);
+        } else {
+            line.extend(&file);
+        }
+    }
+    line
+}
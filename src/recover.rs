@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    confirm, gamedb, load_gamedb, manifest, nauttaja_dir, noita, prompt, save_path, store,
+    unique_name, update_gamedb, CliError, GameDB, Save, BACKUP_NAME_FORMAT, NAUTTAJA_GAMEDB_FILE,
+    NAUTTAJA_SAVES_DIRECTORY, NOITA_SAVE_DIRECTORY, TIMESTAMP_FORMAT,
+};
+
+/// Walks through getting the gamedb back in shape, one step at a time: first
+/// the gamedb itself, then saves whose files are gone and finally save
+/// directories which the gamedb does not know about. Nothing is changed
+/// without asking first.
+pub fn run() -> Result<(), CliError> {
+    say!("Step 1 of 3: checking the gamedb");
+    recover_gamedb()?;
+    let gamedb = match load_gamedb() {
+        Ok(gamedb) => gamedb,
+        Err(CliError::GameDB(_)) => {
+            say!("The gamedb is still malformed, the remaining steps need a readable gamedb");
+            return Ok(());
+        }
+        Err(error) => return Err(error),
+    };
+
+    say!("Step 2 of 3: checking for saves whose files are missing");
+    forget_missing(&gamedb)?;
+
+    say!("Step 3 of 3: checking for save directories missing from the gamedb");
+    adopt_orphans(&load_gamedb()?)?;
+
+    say!("Recovery finished");
+    Ok(())
+}
+
+fn recover_gamedb() -> Result<(), CliError> {
+    let file = nauttaja_dir()?.join(NAUTTAJA_GAMEDB_FILE);
+    let malformed = if file.exists() {
+        let problems = gamedb::problems(&file)?;
+        if problems.is_empty() {
+            say!("The gamedb is fine");
+            return Ok(());
+        }
+        say!("The gamedb is malformed:");
+        for problem in &problems {
+            say!("  {}", problem);
+        }
+        true
+    } else {
+        say!("No gamedb found");
+        false
+    };
+
+    // Newest first, leaving out backups which are malformed themselves
+    let backups: Vec<(PathBuf, GameDB)> = gamedb::backups()?
+        .into_iter()
+        .rev()
+        .filter_map(|backup| gamedb::load(&backup).ok().map(|gamedb| (backup, gamedb)))
+        .collect();
+    if backups.is_empty() && !malformed {
+        say!("No backups of the gamedb found, saves are adopted from their directories in step 3");
+        return Ok(());
+    }
+
+    let mut options = Vec::new();
+    for (backup, gamedb) in &backups {
+        options.push(format!(
+            "Restore the backup [{}], with {} saves and {} removed saves",
+            file_name(backup),
+            gamedb.saves.len(),
+            gamedb.trash.len()
+        ));
+    }
+    if malformed {
+        options.push("Keep the well-formed saves and quarantine the rest".to_string());
+    }
+    for (number, option) in options.iter().enumerate() {
+        say!("{:>4}  {}", number + 1, option);
+    }
+    let choice = loop {
+        let answer = prompt("How to recover the gamedb, or nothing to skip this step:")?;
+        if answer.is_empty() {
+            say!("Skipped, the gamedb has not been touched");
+            return Ok(());
+        }
+        match answer.parse::<usize>() {
+            Ok(choice) if (1..=options.len()).contains(&choice) => break choice - 1,
+            _ => say!("Expected a number from 1 to {}", options.len()),
+        }
+    };
+
+    match backups.get(choice) {
+        Some((backup, _)) => {
+            if let Some(replaced) = gamedb::restore(backup)? {
+                say!("The replaced gamedb was kept as [{}]", replaced.display());
+            }
+            say!("Restored the backup [{}]", file_name(backup));
+        }
+        None => gamedb::repair()?,
+    }
+    Ok(())
+}
+
+fn forget_missing(gamedb: &GameDB) -> Result<(), CliError> {
+    let mut missing = Vec::new();
+    for save in gamedb.saves.iter().chain(gamedb.trash.iter()) {
+        let save_dir = save_path(save)?;
+        if !save_dir.exists() {
+            say!(
+                "  [{}] expected its files in [{}]",
+                save.name,
+                save_dir.display()
+            );
+            missing.push(save.directory.clone());
+        }
+    }
+    if missing.is_empty() {
+        say!("The files of every save are in place");
+        return Ok(());
+    }
+    if !confirm(&format!(
+        "Forget these {} saves? Their files cannot be found anywhere",
+        missing.len()
+    ))? {
+        say!("Skipped, the saves are kept in the gamedb");
+        return Ok(());
+    }
+    update_gamedb(|mut gamedb: GameDB| {
+        gamedb
+            .saves
+            .retain(|save| !missing.contains(&save.directory));
+        gamedb
+            .trash
+            .retain(|save| !missing.contains(&save.directory));
+        if gamedb
+            .loaded
+            .as_ref()
+            .is_some_and(|loaded| missing.contains(&loaded.directory))
+        {
+            gamedb.loaded = None;
+        }
+        gamedb
+    })?;
+    say!("Forgot {} saves", missing.len());
+    Ok(())
+}
+
+// Directories holding a save00 are adopted as saves named after when they were
+// made, which is when their manifest was written if they have one
+fn adopt_orphans(gamedb: &GameDB) -> Result<(), CliError> {
+    let saves_dir = nauttaja_dir()?.join(NAUTTAJA_SAVES_DIRECTORY);
+    if !saves_dir.exists() {
+        say!("No save directories found");
+        return Ok(());
+    }
+    let known: HashSet<&str> = gamedb
+        .saves
+        .iter()
+        .chain(gamedb.trash.iter())
+        .map(|save| save.directory.as_str())
+        .collect();
+    let mut orphans = Vec::new();
+    for entry in fs::read_dir(&saves_dir)? {
+        let entry = entry?;
+        let directory = entry.file_name().to_string_lossy().to_string();
+        if !entry.file_type()?.is_dir() || known.contains(directory.as_str()) {
+            continue;
+        }
+        if !entry.path().join(NOITA_SAVE_DIRECTORY).is_dir() {
+            say!(
+                "  [{}] does not contain a save, leaving it alone",
+                directory
+            );
+            continue;
+        }
+        orphans.push(orphan_save(&entry.path(), directory)?);
+    }
+    if orphans.is_empty() {
+        say!("Every save directory is in the gamedb");
+        return Ok(());
+    }
+    orphans.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    for save in &orphans {
+        say!("  [{}] made {}", save.directory, save.timestamp);
+    }
+    if !confirm(&format!(
+        "Adopt these {} directories as saves?",
+        orphans.len()
+    ))? {
+        say!("Skipped, the directories are left alone");
+        return Ok(());
+    }
+
+    let mut adopted = Vec::new();
+    update_gamedb(|mut gamedb: GameDB| {
+        adopted.clear();
+        for save in &orphans {
+            let name = unique_name(&gamedb, &save.name);
+            adopted.push((save.directory.clone(), name.clone()));
+            gamedb.saves.push(Save {
+                name,
+                ..save.clone()
+            });
+        }
+        gamedb
+    })?;
+    for (directory, name) in &adopted {
+        say!("Adopted [{}] as [{}]", directory, name);
+    }
+    say!("Rename the adopted saves with nauttaja rename <old> <new>");
+    Ok(())
+}
+
+fn orphan_save(save_dir: &Path, directory: String) -> Result<Save, CliError> {
+    let manifest_file = save_dir.join(manifest::MANIFEST_FILE);
+    let modified = if manifest_file.exists() {
+        fs::metadata(&manifest_file)?.modified()?
+    } else {
+        fs::metadata(save_dir)?.modified()?
+    };
+    let created: DateTime<Local> = modified.into();
+    let manifest = manifest::read(save_dir)?;
+    // Overwriting a stored save in place would write into files other saves
+    // link to as well, so the files are hashed when there is no manifest
+    let stored = match &manifest {
+        Some(manifest) => store::has_stored_files(manifest)?,
+        None => store::has_stored_files(&manifest::generate(save_dir)?)?,
+    };
+    Ok(Save {
+        name: format!("recovered-{}", created.format(BACKUP_NAME_FORMAT)),
+        directory,
+        timestamp: created.format(TIMESTAMP_FORMAT).to_string(),
+        size: manifest
+            .as_ref()
+            .map(|manifest| manifest.files.iter().map(|file| file.size).sum()),
+        files: manifest
+            .as_ref()
+            .map(|manifest| manifest.files.len() as u64),
+        index: Some(noita::index(&save_dir.join(NOITA_SAVE_DIRECTORY))),
+        stored,
+        ..Default::default()
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,50 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{CliError, NOITA_SAVE_DIRECTORY};
+
+const SANDBOX_DIRECTORY: &str = "nauttaja-sandbox";
+
+const FAKE_FILES: &[(&str, &str)] = &[
+    (
+        "player.xml",
+        "<Entity name=\"DEBUG_NAME:player\" tags=\"player_unit\">\n  <_Transform position.x=\"227\" position.y=\"-85\" rotation=\"0\" scale.x=\"1\" scale.y=\"1\" />\n  <DamageModelComponent hp=\"4\" max_hp=\"4\" />\n</Entity>\n",
+    ),
+    (
+        "world_state.xml",
+        "<Entity name=\"world_state\">\n  <WorldStateComponent session_stat_file=\"sandbox\">\n    <orbs_found_thisrun />\n  </WorldStateComponent>\n</Entity>\n",
+    ),
+    (
+        "stats/sessions/sandbox_stats.xml",
+        "<Stats>\n  <stats world_seed=\"123456789\" playtime=\"1234.5\" gold=\"312\" enemies_killed=\"27\" />\n</Stats>\n",
+    ),
+    ("world/area_0.bin", "sandbox area 0"),
+    ("world/area_1.bin", "sandbox area 1"),
+    ("world/world_pixel_scenes.bin", "sandbox pixel scenes"),
+];
+
+/// Points nauttaja at a fake Noita installation and an isolated storage root,
+/// both living in the temporary directory. The sandbox is kept between runs,
+/// so every command can be tried in turn, and is created again if deleted.
+pub fn enter() -> Result<PathBuf, CliError> {
+    let sandbox = env::temp_dir().join(SANDBOX_DIRECTORY);
+    let noita_root = sandbox.join("noita");
+    let storage_root = sandbox.join("nauttaja");
+
+    let save_dir = noita_root.join(NOITA_SAVE_DIRECTORY);
+    if !save_dir.exists() {
+        for (path, content) in FAKE_FILES {
+            let path = save_dir.join(path);
+            fs::create_dir_all(path.parent().expect("Fake files live in save00"))?;
+            fs::write(path, content)?;
+        }
+    }
+    fs::create_dir_all(&storage_root)?;
+
+    env::set_var("NAUTTAJA_NOITA_DIR", &noita_root);
+    env::set_var("NAUTTAJA_STORAGE_ROOT", &storage_root);
+    // Keep the real Steam installation out of the sandbox
+    env::set_var("NAUTTAJA_STEAM_DIR", sandbox.join("steam"));
+    Ok(sandbox)
+}
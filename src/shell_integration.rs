@@ -0,0 +1,93 @@
+use std::env;
+use std::io::Error;
+use std::path::Path;
+use std::process::Command;
+
+use crate::{import_save, prompt, CliError, Config};
+
+const IMPORT_KEY: &str = r"HKCU\Software\Classes\Directory\shell\nauttaja.import";
+const OPEN_KEY: &str = r"HKCU\Software\Classes\Directory\Background\shell\nauttaja.open";
+
+pub fn install() -> Result<(), CliError> {
+    if !supported() {
+        return Ok(());
+    }
+    let exe = env::current_exe()?;
+    let exe = exe.display();
+
+    say!("Registering context menu entries in Windows Explorer...");
+    reg(&[
+        "add",
+        IMPORT_KEY,
+        "/ve",
+        "/d",
+        "Import folder as nauttaja save",
+        "/f",
+    ])?;
+    reg(&[
+        "add",
+        &format!(r"{}\command", IMPORT_KEY),
+        "/ve",
+        "/d",
+        &format!("\"{}\" shell-integration import \"%1\"", exe),
+        "/f",
+    ])?;
+    reg(&["add", OPEN_KEY, "/ve", "/d", "Open nauttaja", "/f"])?;
+    reg(&[
+        "add",
+        &format!(r"{}\command", OPEN_KEY),
+        "/ve",
+        "/d",
+        &format!("\"{}\" open", exe),
+        "/f",
+    ])?;
+    say!("Shell integration installed");
+    say!("Run nauttaja shell-integration uninstall to remove it again");
+    Ok(())
+}
+
+pub fn uninstall() -> Result<(), CliError> {
+    if !supported() {
+        return Ok(());
+    }
+    say!("Removing context menu entries from Windows Explorer...");
+    for key in &[IMPORT_KEY, OPEN_KEY] {
+        // Deleting a key which does not exist is not an error worth reporting
+        let _ = reg(&["delete", key, "/f"]);
+    }
+    say!("Shell integration uninstalled");
+    Ok(())
+}
+
+pub fn import(config: &Config, directory: &str) -> Result<(), CliError> {
+    let default_name = Path::new(directory)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = prompt(&format!("Name of the new save [{}]:", default_name))?;
+    let name = if name.is_empty() { default_name } else { name };
+    if let Err(error) = import_save(config, directory, &name, None, false) {
+        say!("Failed to import save: {}", error);
+    }
+    prompt("Press enter to close")?;
+    Ok(())
+}
+
+fn supported() -> bool {
+    if !cfg!(windows) {
+        say!("Shell integration is only available on Windows");
+    }
+    cfg!(windows)
+}
+
+fn reg(args: &[&str]) -> Result<(), CliError> {
+    let status = Command::new("reg").args(args).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(CliError::Io(Error::other(format!(
+            "reg {} failed with {}",
+            args[0], status
+        ))))
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::config::SteamCloudPolicy;
+use crate::{confirm, CliError, Config, SETTLE_TIME_SECS};
+
+const NOITA_APP_ID: &str = "881100";
+const MAX_WAIT_SECS: u64 = 60;
+
+/// Warns when Steam Cloud is enabled for Noita, since Steam may then replace
+/// a freshly loaded save with the files it has stored in the cloud. Returns
+/// whether the load should go ahead.
+pub fn check_before_load(config: &Config) -> Result<bool, CliError> {
+    if config.steam_cloud == SteamCloudPolicy::Ignore {
+        return Ok(true);
+    }
+    let caches = synced_remotecaches(config);
+    if caches.is_empty() {
+        return Ok(true);
+    }
+
+    wait_for_sync(&caches);
+    say!(
+        "Warning: Steam Cloud is enabled for Noita and may replace the loaded save with the files stored in the cloud"
+    );
+    say!("Disable Steam Cloud for Noita in Steam, under Properties > General, before starting the game");
+    match config.steam_cloud {
+        SteamCloudPolicy::Ask => Ok(confirm("Load anyway?")?),
+        _ => Ok(true),
+    }
+}
+
+fn steam_roots(config: &Config) -> Vec<PathBuf> {
+    if let Some(steam_root_dir) = &config.steam_root_dir {
+        return vec![PathBuf::from(steam_root_dir)];
+    }
+    let mut roots = vec![
+        PathBuf::from(r"C:\Program Files (x86)\Steam"),
+        PathBuf::from(r"C:\Program Files\Steam"),
+    ];
+    if let Some(home) = home::home_dir() {
+        roots.push(home.join(".steam").join("steam"));
+        roots.push(home.join(".local").join("share").join("Steam"));
+        roots.push(home.join("Library/Application Support/Steam"));
+    }
+    roots
+}
+
+// Steam keeps a remotecache.vdf per account for every game it syncs
+fn synced_remotecaches(config: &Config) -> Vec<PathBuf> {
+    let mut caches = Vec::new();
+    for root in steam_roots(config) {
+        let accounts = match fs::read_dir(root.join("userdata")) {
+            Ok(accounts) => accounts,
+            Err(_) => continue,
+        };
+        for account in accounts.flatten() {
+            let account = account.path();
+            let cache = account.join(NOITA_APP_ID).join("remotecache.vdf");
+            if cache.exists() && !cloud_disabled(&account) {
+                caches.push(cache);
+            }
+        }
+    }
+    caches
+}
+
+// Looks for "cloudenabled" "0" in the block for Noita in localconfig.vdf
+fn cloud_disabled(account: &Path) -> bool {
+    let localconfig = match fs::read_to_string(account.join("config").join("localconfig.vdf")) {
+        Ok(localconfig) => localconfig.to_lowercase(),
+        Err(_) => return false,
+    };
+    let key = format!("\"{}\"", NOITA_APP_ID);
+    localconfig.match_indices(&key).any(|(index, _)| {
+        let rest = localconfig[index + key.len()..].trim_start();
+        if !rest.starts_with('{') {
+            return false;
+        }
+        let mut depth = 0;
+        let end = rest
+            .char_indices()
+            .find(|(_, c)| {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })
+            .map(|(end, _)| end)
+            .unwrap_or(rest.len());
+        let tokens: Vec<&str> = rest[..end].split_whitespace().collect();
+        tokens
+            .windows(2)
+            .any(|pair| pair == ["\"cloudenabled\"", "\"0\""])
+    })
+}
+
+// A remotecache which changed moments ago means Steam is syncing right now
+fn wait_for_sync(caches: &[PathBuf]) {
+    let settle_time = Duration::from_secs(SETTLE_TIME_SECS);
+    let syncing = || {
+        caches.iter().any(|cache| {
+            fs::metadata(cache)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|elapsed| elapsed < settle_time)
+        })
+    };
+    if !syncing() {
+        return;
+    }
+    say!("Steam Cloud is syncing Noita, waiting for it to finish...");
+    for _ in 0..MAX_WAIT_SECS {
+        thread::sleep(Duration::from_secs(1));
+        if !syncing() {
+            return;
+        }
+    }
+    say!("Steam Cloud is still syncing, continuing anyway");
+}
@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use log::{debug, info};
+
+use crate::manifest::Manifest;
+use crate::{copy, fault, manifest, nauttaja_dir, progress, uuid, CliError};
+
+const NAUTTAJA_STORE_DIRECTORY: &str = "store";
+
+// Files of saves made with dedup_saves are kept here once each, named by their
+// hash, and hard linked into every save having them. Save directories look
+// like any other, so everything reading saves works on them unchanged. Stored
+// files are read-only, since every save linking them shares their permissions.
+fn store_dir() -> Result<PathBuf, CliError> {
+    Ok(nauttaja_dir()?.join(NAUTTAJA_STORE_DIRECTORY))
+}
+
+fn stored_path(store: &Path, hash: &str) -> PathBuf {
+    store.join(&hash[..2]).join(hash)
+}
+
+/// Copies the directory `from` into the directory `to`, hard linking every
+/// file against its copy in the store, which is added to the store when there
+/// is none yet. Returns the manifest of `to`, from the hashes the files are
+/// stored by, along with the number of files which were stored already.
+pub fn copy_dir(from: &Path, to: &Path) -> Result<(Manifest, usize), CliError> {
+    let store = store_dir()?;
+    // Paths in the manifest are relative to `to`, which `from` is copied into
+    let root = from.parent().unwrap_or(from);
+    let mut files = Vec::new();
+    copy::create_dirs(
+        from,
+        &to.join(from.file_name().unwrap_or_default()),
+        &mut files,
+    )?;
+    let total_files = files.len();
+    let total_bytes = files.iter().map(|(_, _, size)| size).sum();
+
+    let entries = Mutex::new(Vec::with_capacity(total_files));
+    let shared = AtomicUsize::new(0);
+    let copied_files = AtomicUsize::new(0);
+    let copied_bytes = AtomicU64::new(0);
+    copy::for_each_parallel(&files, |(source, target, size)| {
+        let entry = manifest::hash_file(root, source)?;
+        let stored = stored_path(&store, &entry.hash);
+        if fs::metadata(&stored).is_ok_and(|metadata| metadata.len() == *size) {
+            shared.fetch_add(1, Ordering::Relaxed);
+        } else {
+            fs::create_dir_all(stored.parent().expect("Stored files are in a directory"))?;
+            // Copied next to its place first, so the store never has a
+            // partial file under the name of a complete one
+            let partial = stored.with_extension(uuid());
+            fs::copy(source, &partial)?;
+            copy::set_readonly(&partial, true)?;
+            fs::rename(&partial, &stored)?;
+        }
+        if fs::hard_link(&stored, target).is_err() {
+            fs::copy(source, target)?;
+        }
+        let copied = copied_files.fetch_add(1, Ordering::Relaxed) + 1;
+        progress::report(
+            "copy",
+            &progress::Update {
+                files: copied,
+                total_files,
+                bytes: copied_bytes.fetch_add(*size, Ordering::Relaxed) + size,
+                total_bytes,
+                file: source,
+            },
+        );
+        entries
+            .lock()
+            .expect("A copying thread panicked")
+            .push(entry);
+        fault::check_partway("copy", copied)
+    })?;
+    progress::finish("copy");
+    fault::check("copy")?;
+    let mut files = entries.into_inner().expect("A copying thread panicked");
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((Manifest { files }, shared.into_inner()))
+}
+
+/// Whether any file in `manifest` is in the store, which only has files of
+/// saves made with dedup_saves. Saves having them are treated as stored, since
+/// their files may be links into the store.
+pub fn has_stored_files(manifest: &Manifest) -> Result<bool, CliError> {
+    let store = store_dir()?;
+    Ok(manifest
+        .files
+        .iter()
+        .any(|entry| stored_path(&store, &entry.hash).exists()))
+}
+
+/// Removes the files from the store which none of the saves in `save_dirs`
+/// has, going by their manifests. Saves keep their own link to every file, so
+/// deleted saves still within their grace period lose nothing, and neither
+/// does a save which is being made. Returns how many files were removed and
+/// how much space they took up in the store.
+pub fn collect_garbage(save_dirs: &[PathBuf]) -> Result<(usize, u64), CliError> {
+    let store = store_dir()?;
+    if !store.exists() {
+        return Ok((0, 0));
+    }
+    let mut used = HashSet::new();
+    for save_dir in save_dirs {
+        if let Some(manifest) = manifest::read(save_dir)? {
+            used.extend(manifest.files.into_iter().map(|entry| entry.hash));
+        }
+    }
+    debug!("{} files in the store are used by saves", used.len());
+
+    let (mut removed, mut freed) = (0, 0);
+    for prefix in fs::read_dir(&store)? {
+        let prefix = prefix?.path();
+        for entry in fs::read_dir(&prefix)? {
+            let entry = entry?;
+            // Files left partially copied are removed as well
+            if used.contains(entry.file_name().to_string_lossy().as_ref()) {
+                continue;
+            }
+            freed += entry.metadata()?.len();
+            copy::set_readonly(&entry.path(), false)?;
+            fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+        if fs::read_dir(&prefix)?.next().is_none() {
+            fs::remove_dir(&prefix)?;
+        }
+    }
+    if removed > 0 {
+        info!(
+            "Removed {} files no save has from [{}]",
+            removed,
+            store.display()
+        );
+    }
+    Ok((removed, freed))
+}
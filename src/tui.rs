@@ -0,0 +1,304 @@
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::config::Config;
+use crate::{
+    confirm, disk, generate_name, human_size, index_saves, load_gamedb, load_gamedb_with_sizes,
+    load_save, low_disk_space, nauttaja_dir, noita_save_dir, output, prompt, remove_saves,
+    restore_saves, save_details, save_game, CliError, GameDB, ListOptions, Save,
+};
+
+// How often the free space shown at the bottom is brought up to date
+const DISK_REFRESH: Duration = Duration::from_secs(30);
+
+const KEYS: &str = "↑↓ select  Tab saves/trash  s save  l load  r remove/restore  q quit";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pane {
+    Saves,
+    Trash,
+}
+
+struct App {
+    gamedb: GameDB,
+    pane: Pane,
+    saves: ListState,
+    trash: ListState,
+    disk: Vec<Line<'static>>,
+    disk_checked: Instant,
+}
+
+/// Shows the saves, the trash and the details of the selected save until quit.
+/// Actions leave the interface while they run, so they ask and report exactly
+/// like the commands they stand for.
+pub fn run(config: &Config) -> Result<(), CliError> {
+    let mut app = App {
+        gamedb: GameDB::default(),
+        pane: Pane::Saves,
+        saves: ListState::default().with_selected(Some(0)),
+        trash: ListState::default().with_selected(Some(0)),
+        disk: disk_usage(config)?,
+        disk_checked: Instant::now(),
+    };
+    app.reload()?;
+
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(config, &mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl App {
+    fn event_loop(
+        &mut self,
+        config: &Config,
+        terminal: &mut DefaultTerminal,
+    ) -> Result<(), CliError> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if self.disk_checked.elapsed() >= DISK_REFRESH {
+                self.disk = disk_usage(config)?;
+                self.disk_checked = Instant::now();
+            }
+            if !event::poll(Duration::from_secs(1))? {
+                continue;
+            }
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Tab | KeyCode::Left | KeyCode::Right => {
+                    self.pane = match self.pane {
+                        Pane::Saves => Pane::Trash,
+                        Pane::Trash => Pane::Saves,
+                    }
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.state().select_previous(),
+                KeyCode::Down | KeyCode::Char('j') => self.state().select_next(),
+                KeyCode::Home => self.state().select_first(),
+                KeyCode::End => self.state().select_last(),
+                KeyCode::Char('s') => self.suspend(terminal, || save(config))?,
+                KeyCode::Char('l') | KeyCode::Enter if self.pane == Pane::Saves => {
+                    if let Some(save) = self.selected().cloned() {
+                        self.suspend(terminal, || load(config, &save))?
+                    }
+                }
+                KeyCode::Char('r') => {
+                    if let Some(save) = self.selected().cloned() {
+                        match self.pane {
+                            Pane::Saves => self.suspend(terminal, || remove(&save))?,
+                            Pane::Trash => self.suspend(terminal, || restore(&save))?,
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn reload(&mut self) -> Result<(), CliError> {
+        let mut gamedb = load_gamedb_with_sizes()?;
+        index_saves(&mut gamedb, false)?;
+        ListOptions::default().sort(&mut gamedb.saves);
+        ListOptions::default().sort(&mut gamedb.trash);
+        self.gamedb = gamedb;
+        Ok(())
+    }
+
+    fn state(&mut self) -> &mut ListState {
+        match self.pane {
+            Pane::Saves => &mut self.saves,
+            Pane::Trash => &mut self.trash,
+        }
+    }
+
+    fn selected(&self) -> Option<&Save> {
+        match self.pane {
+            Pane::Saves => self
+                .saves
+                .selected()
+                .and_then(|index| self.gamedb.saves.get(index)),
+            Pane::Trash => self
+                .trash
+                .selected()
+                .and_then(|index| self.gamedb.trash.get(index)),
+        }
+    }
+
+    // Runs an action outside of the interface, waiting for Enter before coming
+    // back to it
+    fn suspend<F>(&mut self, terminal: &mut DefaultTerminal, action: F) -> Result<(), CliError>
+    where
+        F: FnOnce() -> Result<(), CliError>,
+    {
+        ratatui::restore();
+        if let Err(error) = action() {
+            say!("{}", error);
+        }
+        prompt("Press Enter to go back")?;
+        *terminal = ratatui::init();
+        terminal.clear()?;
+        self.reload()
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, bottom] =
+            Layout::vertical([Constraint::Min(6), Constraint::Length(4)]).areas(frame.area());
+        let [lists, details] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+        let [saves, trash] =
+            Layout::vertical([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(lists);
+
+        let loaded = self
+            .gamedb
+            .loaded
+            .as_ref()
+            .map(|loaded| loaded.directory.as_str());
+        draw_list(
+            frame,
+            saves,
+            "Saves",
+            &self.gamedb.saves,
+            &mut self.saves,
+            self.pane == Pane::Saves,
+            loaded,
+        );
+        draw_list(
+            frame,
+            trash,
+            "Trash",
+            &self.gamedb.trash,
+            &mut self.trash,
+            self.pane == Pane::Trash,
+            loaded,
+        );
+
+        let lines: Vec<Line> = match self.selected() {
+            Some(save) => save_details(&self.gamedb, save)
+                .into_iter()
+                .map(Line::from)
+                .collect(),
+            None => vec![Line::from("Nothing to show")],
+        };
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title(" Details "))
+                .wrap(Wrap { trim: false }),
+            details,
+        );
+
+        let mut status = self.disk.clone();
+        status.push(Line::from(KEYS).dim());
+        frame.render_widget(Paragraph::new(status).block(Block::bordered()), bottom);
+    }
+}
+
+// Lists saves, highlighting the selected one only when the list has focus
+fn draw_list(
+    frame: &mut Frame,
+    area: Rect,
+    title: &str,
+    saves: &[Save],
+    state: &mut ListState,
+    focused: bool,
+    loaded: Option<&str>,
+) {
+    let items: Vec<ListItem> = saves
+        .iter()
+        .map(|save| {
+            let item = ListItem::new(format!("{} - {}", save.timestamp, save.name));
+            if loaded == Some(save.directory.as_str()) {
+                item.bold()
+            } else {
+                item
+            }
+        })
+        .collect();
+    let block = Block::bordered().title(format!(" {} ({}) ", title, saves.len()));
+    let mut list = List::new(items).highlight_symbol("> ");
+    if focused {
+        list = list
+            .block(block.border_style(Style::new().bold()))
+            .highlight_style(Style::new().reversed());
+    } else {
+        list = list.block(block.dim());
+    }
+    frame.render_stateful_widget(list, area, state);
+}
+
+// Free space where the saves and Noita are stored, highlighting low space
+fn disk_usage(config: &Config) -> Result<Vec<Line<'static>>, CliError> {
+    let low: Vec<String> = low_disk_space(config)?
+        .into_iter()
+        .map(|low| low.volume.name)
+        .collect();
+    let mut parts = Vec::new();
+    for (label, path) in [
+        ("Saves", nauttaja_dir()?),
+        ("Noita", PathBuf::from(&config.noita_root_dir)),
+    ] {
+        if let Ok(volume) = disk::volume(&path) {
+            parts.push(format!(
+                "{} on [{}] {} free{}",
+                label,
+                volume.name,
+                human_size(volume.free),
+                if low.contains(&volume.name) {
+                    ", running low"
+                } else {
+                    ""
+                }
+            ));
+        }
+    }
+    let line = Line::from(parts.join("   "));
+    Ok(vec![if low.is_empty() { line } else { line.red() }])
+}
+
+fn save(config: &Config) -> Result<(), CliError> {
+    let save00 = noita_save_dir(config);
+    if !save00.exists() {
+        say!("No current save found, nothing to save");
+        return Ok(());
+    }
+    let gamedb = load_gamedb()?;
+    let generated = generate_name(config, &gamedb, &save00, "")?;
+    let name = prompt(&format!("Name of the save, nothing for [{}]:", generated))?;
+    let name = if name.is_empty() { generated } else { name };
+    save_game(config, &gamedb, &name, None, false, false, false)
+}
+
+fn load(config: &Config, save: &Save) -> Result<(), CliError> {
+    load_save(config, &save.name, true, false, false)
+}
+
+fn remove(save: &Save) -> Result<(), CliError> {
+    if !confirm(&format!("Remove [{}]?", save.name))? {
+        say!("Aborted, nothing was removed");
+        return Ok(());
+    }
+    remove_saves(&load_gamedb()?, &[&save.name])
+}
+
+fn restore(save: &Save) -> Result<(), CliError> {
+    restore_saves(&load_gamedb()?, &[&save.name])
+}
+
+/// Whether there is a terminal to show the interface in.
+pub fn available() -> bool {
+    !output::is_json() && io::stdin().is_terminal() && io::stdout().is_terminal()
+}
@@ -0,0 +1,205 @@
+//! Runs nauttaja with --inject-fault against a fake Noita install, checking
+//! that failed and interrupted operations leave the saves recoverable.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Output};
+
+const FAKE_FILES: &[(&str, &str)] = &[
+    (
+        "player.xml",
+        "<Entity name=\"DEBUG_NAME:player\" tags=\"player_unit\">\n  <_Transform position.x=\"227\" position.y=\"-85\" rotation=\"0\" scale.x=\"1\" scale.y=\"1\" />\n  <DamageModelComponent hp=\"4\" max_hp=\"4\" />\n</Entity>\n",
+    ),
+    (
+        "world_state.xml",
+        "<Entity name=\"world_state\">\n  <WorldStateComponent session_stat_file=\"test\">\n    <orbs_found_thisrun />\n  </WorldStateComponent>\n</Entity>\n",
+    ),
+    (
+        "stats/sessions/test_stats.xml",
+        "<Stats>\n  <stats world_seed=\"123456789\" playtime=\"1234.5\" gold=\"312\" enemies_killed=\"27\" />\n</Stats>\n",
+    ),
+    ("world/area_0.bin", "area 0"),
+    ("world/area_1.bin", "area 1"),
+    ("world/area_2.bin", "area 2"),
+    ("world/world_pixel_scenes.bin", "pixel scenes"),
+];
+
+// A fake Noita install and storage root of its own, removed once done with
+struct Install {
+    root: PathBuf,
+}
+
+impl Install {
+    fn new(name: &str) -> Install {
+        let root = env::temp_dir().join(format!("nauttaja-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let install = Install { root };
+        for (path, content) in FAKE_FILES {
+            install.write(path, content);
+        }
+        install
+    }
+
+    fn save00(&self) -> PathBuf {
+        self.root.join("noita").join("save00")
+    }
+
+    fn storage(&self) -> PathBuf {
+        self.root.join("nauttaja")
+    }
+
+    fn write(&self, path: &str, content: &str) {
+        let path = self.save00().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.save00().join(path)).unwrap()
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.run_with_engines(r#"["plain"]"#, args)
+    }
+
+    fn run_with_engines(&self, engines: &str, args: &[&str]) -> Output {
+        self.command(args)
+            .env("NAUTTAJA_COPY_ENGINES", engines)
+            .output()
+            .unwrap()
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_nauttaja"));
+        command
+            .args(["--progress", "none", "--yes"])
+            .args(args)
+            .env("NAUTTAJA_NOITA_DIR", self.root.join("noita"))
+            .env("NAUTTAJA_STORAGE_ROOT", self.storage())
+            .env("NAUTTAJA_STEAM_DIR", self.root.join("steam"))
+            .env("NAUTTAJA_COPY_ENGINES", r#"["plain"]"#)
+            .env_remove("NAUTTAJA_LOG");
+        command
+    }
+
+    fn succeed(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(output.status.success(), "{:?} failed: {:?}", args, output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+}
+
+impl Drop for Install {
+    fn drop(&mut self) {
+        let _ = make_writable(&self.root);
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+// Saves may be read-only, which keeps them from being removed on Windows
+fn make_writable(path: &Path) -> std::io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    fs::set_permissions(path, permissions)?;
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            make_writable(&entry?.path())?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn load_failing_partway_is_rolled_back() {
+    let install = Install::new("rollback");
+    install.succeed(&["save", "first"]);
+    install.write("world/area_0.bin", "changed since");
+
+    let output = install.run(&["load", "first", "--inject-fault", "copy:after=2"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Rolled back successfully"));
+    assert_eq!(install.read("world/area_0.bin"), "changed since");
+    assert_eq!(install.read("world/area_1.bin"), "area 1");
+}
+
+#[test]
+fn load_crashing_partway_leaves_the_backup() {
+    let install = Install::new("crash");
+    install.succeed(&["save", "first"]);
+    install.write("world/area_0.bin", "changed since");
+
+    let output = install.run(&["load", "first", "--inject-fault", "copy:after=2:crash"]);
+    assert!(!output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Rolled back"));
+
+    let backups: Vec<PathBuf> = fs::read_dir(install.storage().join("backup"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(backups.len(), 1);
+    let backed_up = backups[0].join("save00").join("world").join("area_0.bin");
+    assert_eq!(fs::read_to_string(backed_up).unwrap(), "changed since");
+
+    // Nothing is left locked, so the load can simply be run again
+    install.succeed(&["load", "first"]);
+    assert_eq!(install.read("world/area_0.bin"), "area 0");
+}
+
+#[test]
+fn save_crashing_before_the_gamedb_is_written_is_recovered() {
+    let install = Install::new("recover");
+    install.succeed(&["save", "first"]);
+    install.write("world/area_0.bin", "changed since");
+
+    let output = install.run(&["save", "second", "--inject-fault", "gamedb:crash"]);
+    assert!(!output.status.success());
+    assert!(!install.succeed(&["list"]).contains("second"));
+
+    let recovered = install.succeed(&["recover"]);
+    assert!(recovered.contains("Adopted"), "{}", recovered);
+    let list = install.succeed(&["list"]);
+    assert!(list.contains("first"));
+    assert!(list.contains("recovered-"));
+    assert!(install
+        .succeed(&["verify", "--all"])
+        .contains("0 with problems"));
+}
+
+#[test]
+fn stored_save_is_recovered_as_stored() {
+    let install = Install::new("stored");
+    let output = install
+        .command(&["save", "first", "--inject-fault", "gamedb:crash"])
+        .env("NAUTTAJA_DEDUP_SAVES", "true")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    install.succeed(&["recover"]);
+    let list = install.succeed(&["--json", "list"]);
+    assert!(list.contains("\"stored\":true"), "{}", list);
+}
+
+#[test]
+fn engine_failing_partway_falls_back_to_the_next() {
+    let install = Install::new("fallback");
+    let output = install.run_with_engines(
+        r#"["parallel", "plain"]"#,
+        &["save", "first", "--inject-fault", "copy:after=1"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert!(install
+        .succeed(&["verify", "--all"])
+        .contains("0 with problems"));
+}
+
+#[test]
+fn unknown_fault_is_refused() {
+    let install = Install::new("unknown");
+    let output = install.run(&["list", "--inject-fault", "gamedb:after=1"]);
+    assert!(!output.status.success());
+    let output = install.run(&["list", "--inject-fault", "nowhere"]);
+    assert!(!output.status.success());
+}